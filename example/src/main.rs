@@ -46,7 +46,7 @@ impl App {
         Task::none()
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         if let Some(frames) = self.frames.as_ref() {
             container(gif(frames))
                 .center_x(Length::Fill)
@@ -2,7 +2,25 @@ pub mod gif;
 
 pub use gif::Gif;
 
+use iced_widget::core::{image, Element};
+
 /// Creates a new [`Gif`] with the given [`gif::Frames`]
-pub fn gif(frames: &gif::Frames) -> Gif {
+pub fn gif<Message>(frames: &gif::Frames) -> Gif<'_, Message> {
     Gif::new(frames)
 }
+
+/// Creates a new [`Gif`] if the [`gif::Frames`] loaded, or the
+/// `fallback` for the [`gif::Error`] otherwise
+pub fn gif_or<'a, Message, Theme, Renderer>(
+    frames: &'a Result<gif::Frames, gif::Error>,
+    fallback: impl FnOnce(&'a gif::Error) -> Element<'a, Message, Theme, Renderer>,
+) -> Element<'a, Message, Theme, Renderer>
+where
+    Message: 'a,
+    Renderer: image::Renderer<Handle = image::Handle> + 'a,
+{
+    match frames {
+        Ok(frames) => Gif::new(frames).into(),
+        Err(error) => fallback(error),
+    }
+}
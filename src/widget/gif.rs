@@ -1,25 +1,48 @@
 //! Display a GIF in your user interface
+mod lazy;
+mod rounded;
+#[cfg(test)]
+mod tests;
+
+use std::borrow::Cow;
 use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io;
 use std::path::Path;
+use std::rc::Rc;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+#[cfg(any(feature = "async-fs", feature = "tokio"))]
+use std::{collections::HashMap, path::PathBuf, sync::OnceLock};
 
+#[cfg(any(feature = "async-fs", feature = "tokio"))]
+use iced_futures::MaybeSend;
 #[allow(unused)]
 use iced_widget::core::image::Image;
 use iced_widget::core::image::{self, FilterMethod, Handle};
-use iced_widget::core::mouse::Cursor;
-use iced_widget::core::widget::{tree, Tree};
+use iced_widget::core::mouse::{self, Cursor};
+use iced_widget::core::widget::{operation, tree, Id, Operation, Tree};
 use iced_widget::core::{
-    event, layout, renderer, window, Clipboard, ContentFit, Element, Event, Layout, Length, Point,
-    Rectangle, Rotation, Shell, Size, Vector, Widget,
+    border, event, layout, renderer, touch, window, Clipboard, Color, ContentFit, Element, Event,
+    Layout, Length, Point, Rectangle, Rotation, Shell, Size, Vector, Widget,
 };
-use iced_widget::runtime::Task;
+use iced_widget::runtime::{task, Action, Task};
 use image_rs::codecs::gif;
 use image_rs::{AnimationDecoder, ImageDecoder};
 
-#[cfg(not(feature = "tokio"))]
+#[cfg(feature = "apng")]
+use image_rs::codecs::png;
+#[cfg(feature = "webp")]
+use image_rs::codecs::webp;
+
+#[cfg(all(feature = "async-fs", not(feature = "tokio")))]
+use async_fs::File;
+#[cfg(all(feature = "async-fs", not(feature = "tokio")))]
 use iced_futures::futures::{AsyncRead, AsyncReadExt};
 #[cfg(feature = "tokio")]
+use tokio::fs::File;
+#[cfg(feature = "tokio")]
 use tokio::io::{AsyncRead, AsyncReadExt};
 
 /// Error loading or decoding a gif
@@ -31,342 +54,3371 @@ pub enum Error {
     /// Load error
     #[error(transparent)]
     Io(#[from] std::io::Error),
+    /// The image contains no frames
+    #[error("image contains no frames")]
+    NoFrames,
+    /// The image is not in a supported format
+    #[error(
+        "unsupported image format{}",
+        .detected.map_or(String::new(), |format| format!(": {format:?}"))
+    )]
+    UnsupportedFormat {
+        /// The format detected from the image's magic bytes, if they
+        /// were recognized at all
+        detected: Option<image_rs::ImageFormat>,
+    },
+    /// A [`DecodeLimits`] or the byte limit of
+    /// `Frames::from_reader_limited` was exceeded
+    #[error("decode limit exceeded")]
+    LimitExceeded,
+    /// Reading from the source failed partway through
+    #[error("failed to read gif: {0}")]
+    Read(#[source] io::Error),
+    /// The bytes read from the source aren't a valid image
+    #[error("failed to decode gif: {0}")]
+    Decode(#[source] image_rs::ImageError),
+    /// The background decoding task failed to complete
+    #[error("blocking decode task failed")]
+    BlockingTask,
 }
 
-/// The frames of a decoded gif
-pub struct Frames {
-    first: Frame,
-    frames: Vec<Frame>,
-    total_bytes: u64,
-}
-
-impl fmt::Debug for Frames {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Frames").finish()
+#[cfg(any(feature = "async-fs", feature = "tokio"))]
+impl Error {
+    /// Marks an image error from decoding read bytes as [`Error::Decode`]
+    fn into_decode(self) -> Self {
+        match self {
+            Error::Image(error) => Error::Decode(error),
+            error => error,
+        }
     }
 }
 
-impl Frames {
-    /// Load [`Frames`] from the supplied path
-    pub fn load_from_path(path: impl AsRef<Path>) -> Task<Result<Frames, Error>> {
-        #[cfg(feature = "tokio")]
-        use tokio::fs::File;
-        #[cfg(feature = "tokio")]
-        use tokio::io::BufReader;
+/// The playback position of a [`Gif`], produced by [`query_playback`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Playback {
+    /// The index of the displayed frame
+    pub index: usize,
+    /// The total number of frames
+    pub total: usize,
+    /// How long the displayed frame has been on screen, by the
+    /// [`Gif::clock`] if it's set
+    ///
+    /// This stops counting while playback is paused or otherwise held.
+    pub elapsed: Duration,
+}
 
-        #[cfg(not(feature = "tokio"))]
-        use async_fs::File;
-        #[cfg(not(feature = "tokio"))]
-        use iced_futures::futures::io::BufReader;
+/// Queries the [`Playback`] of the [`Gif`] with the given [`Id`]
+///
+/// Produces `None` if no [`Gif`] has the [`Id`].
+pub fn query_playback(id: Id) -> Task<Option<Playback>> {
+    struct QueryPlayback {
+        id: Id,
+        playback: Option<Playback>,
+    }
 
-        let path = path.as_ref().to_path_buf();
+    impl Operation<Option<Playback>> for QueryPlayback {
+        fn container(
+            &mut self,
+            _id: Option<&Id>,
+            _bounds: Rectangle,
+            operate_on_children: &mut dyn FnMut(&mut dyn Operation<Option<Playback>>),
+        ) {
+            operate_on_children(self);
+        }
 
-        let f = async move {
-            let reader = BufReader::new(File::open(path).await?);
+        fn custom(&mut self, state: &mut dyn std::any::Any, id: Option<&Id>) {
+            if id != Some(&self.id) {
+                return;
+            }
 
-            Self::from_reader(reader).await
-        };
+            if let Some(state) = state.downcast_ref::<State>() {
+                self.playback = Some(Playback {
+                    index: state.index,
+                    total: state.len,
+                    elapsed: state.elapsed,
+                });
+            }
+        }
 
-        Task::perform(f, std::convert::identity)
+        fn finish(&self) -> operation::Outcome<Option<Playback>> {
+            operation::Outcome::Some(self.playback)
+        }
     }
 
-    /// Decode [`Frames`] from the supplied async reader
-    pub async fn from_reader<R: AsyncRead>(reader: R) -> Result<Self, Error> {
-        use iced_futures::futures::pin_mut;
-
-        pin_mut!(reader);
+    task::widget(QueryPlayback { id, playback: None })
+}
 
-        let mut bytes = vec![];
+/// Steps the paused [`Gif`] with the given [`Id`] one frame forward,
+/// wrapping around to the first frame
+///
+/// The step applies on the next event the [`Gif`] handles, which
+/// produces its frame messages and redraws it.
+pub fn step_forward<T>(id: Id) -> Task<T> {
+    step(id, Direction::Forward)
+}
 
-        reader.read_to_end(&mut bytes).await?;
+/// Steps the paused [`Gif`] with the given [`Id`] one frame backward,
+/// wrapping around to the last frame
+///
+/// The step applies like [`step_forward`] does.
+pub fn step_backward<T>(id: Id) -> Task<T> {
+    step(id, Direction::Backward)
+}
 
-        Self::from_bytes(bytes)
+fn step<T>(id: Id, direction: Direction) -> Task<T> {
+    struct Step {
+        id: Id,
+        direction: Direction,
     }
 
-    /// Decode [`Frames`] from the supplied bytes
-    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, Error> {
-        let decoder = gif::GifDecoder::new(io::Cursor::new(bytes))?;
+    impl Operation for Step {
+        fn container(
+            &mut self,
+            _id: Option<&Id>,
+            _bounds: Rectangle,
+            operate_on_children: &mut dyn FnMut(&mut dyn Operation),
+        ) {
+            operate_on_children(self);
+        }
 
-        let total_bytes = decoder.total_bytes();
+        fn custom(&mut self, state: &mut dyn std::any::Any, id: Option<&Id>) {
+            if id != Some(&self.id) {
+                return;
+            }
 
-        let frames = decoder
-            .into_frames()
-            .into_iter()
-            .map(|result| result.map(Frame::from))
-            .collect::<Result<Vec<_>, _>>()?;
+            if let Some(state) = state.downcast_mut::<State>() {
+                state.step = Some(self.direction);
+            }
+        }
+    }
 
-        let first = frames.first().cloned().unwrap();
+    task::effect(Action::widget(Step { id, direction }))
+}
 
-        Ok(Frames {
-            total_bytes,
-            first,
-            frames,
-        })
+/// Limits on the resources used decoding a gif
+///
+/// Use these with [`Frames::from_bytes_limited`] when decoding untrusted input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// The maximum total bytes of all decoded frames
+    pub max_total_bytes: u64,
+    /// The maximum number of frames
+    pub max_frames: usize,
+    /// The maximum width of the gif, in pixels
+    pub max_width: u32,
+    /// The maximum height of the gif, in pixels
+    pub max_height: u32,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_total_bytes: 512 * 1024 * 1024,
+            max_frames: 10_000,
+            max_width: 8192,
+            max_height: 8192,
+        }
     }
 }
 
-#[derive(Clone)]
-struct Frame {
-    delay: Duration,
-    handle: image::Handle,
+/// The progress of loading [`Frames`] with
+/// [`Frames::load_from_reader_with_progress`]
+#[cfg(any(feature = "async-fs", feature = "tokio"))]
+#[derive(Debug)]
+pub enum Loading {
+    /// Some bytes have been read
+    Progress {
+        /// The number of bytes read so far
+        read: u64,
+        /// The total number of bytes, if known
+        total: Option<u64>,
+    },
+    /// Loading has finished
+    Done(Result<Frames, Error>),
 }
 
-impl From<image_rs::Frame> for Frame {
-    fn from(frame: image_rs::Frame) -> Self {
-        let (width, height) = frame.buffer().dimensions();
+/// The number of times a gif repeats its animation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoopCount {
+    /// Repeat forever
+    #[default]
+    Infinite,
+    /// Repeat the given number of times after the first play
+    ///
+    /// `Finite(0)` plays the animation once.
+    Finite(u32),
+}
 
-        let delay = frame.delay().into();
+impl From<gif_rs::Repeat> for LoopCount {
+    fn from(repeat: gif_rs::Repeat) -> Self {
+        match repeat {
+            gif_rs::Repeat::Infinite => LoopCount::Infinite,
+            gif_rs::Repeat::Finite(count) => LoopCount::Finite(count.into()),
+        }
+    }
+}
 
-        let handle = image::Handle::from_rgba(width, height, frame.into_buffer().into_vec());
+/// What a [`Gif`] shows once [`Gif::play_once_then`] has held its
+/// last frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndAction {
+    /// Go back to the frame playback started from, such as the one
+    /// given to [`Gif::paused_on`], or the last frame when playing
+    /// [`Direction::Backward`]
+    HoldFirst,
+    /// Keep showing the last frame
+    HoldLast,
+    /// Draw nothing and lay out with no size, collapsing the [`Gif`]
+    Hide,
+}
 
-        Self { delay, handle }
-    }
+/// The direction a [`Gif`] plays its frames in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Direction {
+    /// Play from the first frame to the last
+    #[default]
+    Forward,
+    /// Play from the last frame to the first
+    Backward,
 }
 
-struct State {
-    index: usize,
-    current: Current,
-    total_bytes: u64,
+/// How a [`Gif`] schedules the redraws that advance its frames
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RedrawMode {
+    /// Request a redraw at the time the next frame is due
+    #[default]
+    Timed,
+    /// Request a redraw every frame, advancing by the wall clock
+    ///
+    /// This uses more CPU, but plays smoothly on backends that
+    /// don't honor timed redraws precisely.
+    Continuous,
 }
 
-struct Current {
-    frame: Frame,
-    started: Instant,
+/// The frames of a decoded gif
+///
+/// Cloning is cheap, as the decoded pixels are shared.
+#[derive(Clone)]
+pub struct Frames {
+    first: Frame,
+    source: Source,
+    hash: u64,
+    /// The time each frame ends at into a single loop
+    ends: Vec<Duration>,
+    loop_count: LoopCount,
+    warnings: Vec<String>,
+    /// The copies made by [`Frames::flipped`], shared by every clone,
+    /// flipped horizontally, vertically and both ways
+    flipped: Arc<Mutex<[Option<Frames>; 3]>>,
 }
 
-impl From<Frame> for Current {
-    fn from(frame: Frame) -> Self {
-        Self {
-            started: Instant::now(),
-            frame,
-        }
-    }
+#[derive(Clone)]
+enum Source {
+    Decoded(Vec<Frame>),
+    Lazy(Box<lazy::Lazy>),
 }
 
-/// A frame that displays a GIF while keeping aspect ratio
-#[derive(Debug)]
-pub struct Gif<'a> {
-    frames: &'a Frames,
-    width: Length,
-    height: Length,
-    content_fit: ContentFit,
-    filter_method: FilterMethod,
-    rotation: Rotation,
-    opacity: f32,
+impl fmt::Debug for Frames {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Frames").finish()
+    }
 }
 
-impl<'a> Gif<'a> {
-    /// Creates a new [`Gif`] with the given [`Frames`]
-    pub fn new(frames: &'a Frames) -> Self {
-        Gif {
-            frames,
-            width: Length::Shrink,
-            height: Length::Shrink,
-            content_fit: ContentFit::default(),
-            filter_method: FilterMethod::default(),
-            rotation: Rotation::default(),
-            opacity: 1.0,
-        }
+impl Default for Frames {
+    fn default() -> Self {
+        Self::empty()
     }
+}
 
-    /// Sets the width of the [`Gif`] boundaries.
-    pub fn width(mut self, width: Length) -> Self {
-        self.width = width;
-        self
+/// [`Frames`] compare by a hash of their first frame and delays,
+/// computed once when decoded, rather than by every pixel
+///
+/// Two different gifs are very unlikely to compare equal, but this
+/// isn't a byte-exact comparison. The partial frames produced by
+/// [`Frames::stream_from_bytes`] all compare equal.
+impl PartialEq for Frames {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
     }
+}
 
-    /// Sets the height of the [`Gif`] boundaries.
-    pub fn height(mut self, height: Length) -> Self {
-        self.height = height;
-        self
+impl Eq for Frames {}
+
+impl Hash for Frames {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash.hash(state);
     }
+}
 
-    /// Sets the [`ContentFit`] of the [`Image`].
+impl Frames {
+    /// Creates [`Frames`] with no frames
     ///
-    /// Defaults to [`ContentFit::Contain`]
-    pub fn content_fit(mut self, content_fit: ContentFit) -> Self {
-        self.content_fit = content_fit;
-        self
-    }
+    /// A [`Gif`] of these frames lays out as a single transparent
+    /// pixel and draws nothing, so it can stand in until real
+    /// frames have loaded.
+    pub fn empty() -> Self {
+        let first = Frame {
+            delay: Duration::ZERO,
+            handle: Handle::from_rgba(1, 1, vec![0; 4]),
+        };
 
-    /// Sets the [`FilterMethod`] of the [`Image`].
-    pub fn filter_method(mut self, filter_method: FilterMethod) -> Self {
-        self.filter_method = filter_method;
-        self
+        Self::new(first, Source::Decoded(vec![]), LoopCount::Infinite)
     }
 
-    /// Applies the given [`Rotation`] to the [`Image`].
-    pub fn rotation(mut self, rotation: impl Into<Rotation>) -> Self {
-        self.rotation = rotation.into();
-        self
+    /// Load [`Frames`] from the supplied path, blocking until decoded
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Frames, Error> {
+        Self::from_bytes(std::fs::read(path)?)
     }
+}
 
-    /// Sets the opacity of the [`Image`].
+/// Loading from files and readers, which needs the `async-fs` or
+/// `tokio` feature
+#[cfg(any(feature = "async-fs", feature = "tokio"))]
+impl Frames {
+    /// Load [`Frames`] from the supplied path
     ///
-    /// It should be in the [0.0, 1.0] range—`0.0` meaning completely transparent,
-    /// and `1.0` meaning completely opaque.
-    pub fn opacity(mut self, opacity: impl Into<f32>) -> Self {
-        self.opacity = opacity.into();
-        self
-    }
-}
+    /// Aborting the task, e.g. one made [`Task::abortable`], stops
+    /// decoding before the next frame.
+    pub fn load_from_path(path: impl AsRef<Path>) -> Task<Result<Frames, Error>> {
+        let path = path.as_ref().to_path_buf();
 
-impl<'a, Message, Theme, Renderer> Widget<Message, Theme, Renderer> for Gif<'a>
-where
-    Renderer: image::Renderer<Handle = Handle>,
-{
-    fn size(&self) -> Size<Length> {
-        Size::new(self.width, self.height)
+        Task::perform(Self::read_path(path), std::convert::identity)
     }
 
-    fn tag(&self) -> tree::Tag {
-        tree::Tag::of::<State>()
-    }
+    /// Load [`Frames`] from the supplied path, reusing the frames of
+    /// an earlier cached load of the same file
+    ///
+    /// Cached frames are shared rather than decoded again, which helps
+    /// when the same gif appears many times. Once the cache holds
+    /// [`Frames::set_cache_capacity`] gifs, the least recently
+    /// used is evicted.
+    pub fn load_from_path_cached(path: impl AsRef<Path>) -> Task<Result<Frames, Error>> {
+        let path = path.as_ref().to_path_buf();
 
-    fn state(&self) -> tree::State {
-        tree::State::new(State {
-            index: 0,
-            current: self.frames.first.clone().into(),
-            total_bytes: self.frames.total_bytes,
-        })
-    }
+        let f = async move {
+            let path = std::fs::canonicalize(&path).unwrap_or(path);
 
-    fn diff(&self, tree: &mut Tree) {
-        let state = tree.state.downcast_mut::<State>();
+            if let Some(frames) = cache().get(&path) {
+                return Ok(frames);
+            }
 
-        // Reset state if new gif Frames is used w/
-        // same state tree.
-        //
-        // Total bytes of the gif should be a good enough
-        // proxy for it changing.
-        if state.total_bytes != self.frames.total_bytes {
-            *state = State {
-                index: 0,
-                current: self.frames.first.clone().into(),
-                total_bytes: self.frames.total_bytes,
-            };
-        }
+            let frames = Self::read_path(path.clone()).await?;
+
+            cache().insert(path, frames.clone());
+
+            Ok(frames)
+        };
+
+        Task::perform(f, std::convert::identity)
     }
 
-    fn layout(
-        &self,
-        _tree: &mut Tree,
-        renderer: &Renderer,
-        limits: &layout::Limits,
-    ) -> layout::Node {
-        iced_widget::image::layout(
-            renderer,
-            limits,
-            &self.frames.first.handle,
-            self.width,
-            self.height,
-            self.content_fit,
-            self.rotation,
-        )
+    /// Sets how many gifs [`Frames::load_from_path_cached`] keeps
+    /// cached, evicting the least recently used beyond it
+    ///
+    /// Defaults to 64.
+    pub fn set_cache_capacity(capacity: usize) {
+        let mut cache = cache();
+
+        cache.capacity = capacity;
+        cache.evict();
     }
 
-    fn on_event(
-        &mut self,
-        tree: &mut Tree,
-        event: Event,
-        _layout: Layout<'_>,
-        _cursor: Cursor,
-        _renderer: &Renderer,
-        _clipboard: &mut dyn Clipboard,
-        shell: &mut Shell<'_, Message>,
-        _viewport: &Rectangle,
-    ) -> event::Status {
-        let state = tree.state.downcast_mut::<State>();
+    /// Load [`Frames`] from the supplied path once no newer load with
+    /// the same key has been requested for the given window
+    ///
+    /// A load that's superseded within the window completes without
+    /// producing anything, so rapidly recycled list items don't decode
+    /// gifs that are immediately thrown away.
+    pub fn load_from_path_debounced(
+        path: impl AsRef<Path>,
+        key: impl Hash,
+        window: Duration,
+    ) -> Task<Result<Frames, Error>> {
+        static LATEST: OnceLock<Mutex<HashMap<u64, u64>>> = OnceLock::new();
 
-        if let Event::Window(window::Event::RedrawRequested(now)) = event {
-            let elapsed = now.duration_since(state.current.started);
+        let latest = || {
+            LATEST
+                .get_or_init(Default::default)
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+        };
 
-            if elapsed > state.current.frame.delay {
-                state.index = (state.index + 1) % self.frames.frames.len();
+        let path = path.as_ref().to_path_buf();
 
-                state.current = self.frames.frames[state.index].clone().into();
+        let key = {
+            let mut hasher = DefaultHasher::new();
+            key.hash(&mut hasher);
+            hasher.finish()
+        };
 
-                shell.request_redraw(window::RedrawRequest::At(now + state.current.frame.delay));
-            } else {
-                let remaining = state.current.frame.delay - elapsed;
+        let generation = {
+            let mut latest = latest();
+            let generation = latest.entry(key).or_default();
+            *generation = generation.wrapping_add(1);
+            *generation
+        };
+
+        let f = async move {
+            sleep(window).await;
 
-                shell.request_redraw(window::RedrawRequest::At(now + remaining));
+            // Only the newest load for the key goes on to decode
+            if latest().get(&key) != Some(&generation) {
+                return None;
             }
-        }
 
-        event::Status::Ignored
+            let result = Self::read_path(path).await;
+
+            let mut latest = latest();
+
+            if latest.get(&key) == Some(&generation) {
+                latest.remove(&key);
+            }
+
+            Some(result)
+        };
+
+        Task::future(f).then(|result| match result {
+            Some(result) => Task::done(result),
+            None => Task::none(),
+        })
     }
 
-    fn draw(
-        &self,
-        tree: &Tree,
-        renderer: &mut Renderer,
-        _theme: &Theme,
-        _style: &renderer::Style,
-        layout: Layout<'_>,
-        _cursor: Cursor,
-        _viewport: &Rectangle,
-    ) {
-        let state = tree.state.downcast_ref::<State>();
+    /// Load [`Frames`] from the supplied, already open file
+    ///
+    /// This is a `tokio::fs::File` with the `tokio` feature enabled,
+    /// and an `async_fs::File` otherwise.
+    pub fn from_file(file: File) -> Task<Result<Frames, Error>> {
+        Task::perform(Self::read_file(file), std::convert::identity)
+    }
 
-        // Pulled from iced_native::widget::<Image as Widget>::draw
-        //
-        // TODO: export iced_native::widget::image::draw as standalone function
-        {
-            let Size { width, height } = renderer.measure_image(&state.current.frame.handle);
-            let image_size = Size::new(width as f32, height as f32);
-            let rotated_size = self.rotation.apply(image_size);
+    async fn read_path(path: PathBuf) -> Result<Frames, Error> {
+        Self::read_file(File::open(path).await?).await
+    }
 
-            let bounds = layout.bounds();
-            let adjusted_fit = self.content_fit.fit(rotated_size, bounds.size());
+    async fn read_file(file: File) -> Result<Frames, Error> {
+        #[cfg(feature = "tokio")]
+        use tokio::io::BufReader;
 
-            let scale = Vector::new(
-                adjusted_fit.width / rotated_size.width,
-                adjusted_fit.height / rotated_size.height,
-            );
+        #[cfg(not(feature = "tokio"))]
+        use iced_futures::futures::io::BufReader;
 
-            let final_size = image_size * scale;
-
-            let position = match self.content_fit {
-                ContentFit::None => Point::new(
-                    bounds.x + (rotated_size.width - adjusted_fit.width) / 2.0,
-                    bounds.y + (rotated_size.height - adjusted_fit.height) / 2.0,
-                ),
-                _ => Point::new(
-                    bounds.center_x() - final_size.width / 2.0,
-                    bounds.center_y() - final_size.height / 2.0,
-                ),
-            };
+        Self::from_reader(BufReader::new(file)).await
+    }
 
-            let drawing_bounds = Rectangle::new(position, final_size);
-
-            let render = |renderer: &mut Renderer| {
-                renderer.draw_image(
-                    image::Image {
-                        handle: state.current.frame.handle.clone(),
-                        filter_method: self.filter_method,
-                        rotation: self.rotation.radians(),
-                        opacity: self.opacity,
-                        snap: true,
-                    },
-                    drawing_bounds,
-                );
-            };
+    /// Decode [`Frames`] from the supplied async reader
+    ///
+    /// Failing to read fails with [`Error::Read`], while bytes that
+    /// fail to decode fail with [`Error::Decode`]. Dropping the future
+    /// stops decoding before the next frame.
+    pub async fn from_reader<R: AsyncRead>(reader: R) -> Result<Self, Error> {
+        use iced_futures::futures::pin_mut;
 
-            if adjusted_fit.width > bounds.width || adjusted_fit.height > bounds.height {
-                renderer.with_layer(bounds, render);
-            } else {
-                render(renderer);
-            }
-        }
+        pin_mut!(reader);
+
+        let mut bytes = vec![];
+
+        reader.read_to_end(&mut bytes).await.map_err(Error::Read)?;
+
+        decode_in_background(bytes).await
+    }
+
+    /// Decode [`Frames`] from the supplied async reader, reading at
+    /// most `max_bytes`
+    ///
+    /// Fails with [`Error::LimitExceeded`] as soon as the reader
+    /// produces more than `max_bytes`, before anything is decoded.
+    /// Use this over [`Frames::from_reader`] for sources whose length
+    /// can't be trusted.
+    pub async fn from_reader_limited<R: AsyncRead>(
+        reader: R,
+        max_bytes: usize,
+    ) -> Result<Self, Error> {
+        use iced_futures::futures::pin_mut;
+
+        pin_mut!(reader);
+
+        let mut bytes = vec![];
+        let mut buffer = vec![0; 8 * 1024];
+
+        loop {
+            let read = reader.read(&mut buffer).await.map_err(Error::Read)?;
+
+            if read == 0 {
+                break;
+            }
+
+            if bytes.len() + read > max_bytes {
+                return Err(Error::LimitExceeded);
+            }
+
+            bytes.extend_from_slice(&buffer[..read]);
+        }
+
+        decode_in_background(bytes).await
+    }
+
+    /// Decode [`Frames`] from the supplied async reader while it's
+    /// still being read, producing the frames decoded so far
+    ///
+    /// Each frame is decoded as soon as its bytes are read, so a slow
+    /// source starts animating long before it's done. Frames are
+    /// produced like [`Frames::stream_from_bytes`] does. Failing to
+    /// read ends the stream with [`Error::Read`], after whatever was
+    /// already produced.
+    pub fn stream_from_reader<R>(reader: R) -> Task<Result<Frames, Error>>
+    where
+        R: AsyncRead + MaybeSend + 'static,
+    {
+        use iced_futures::futures::channel::mpsc;
+        use iced_futures::futures::{future, pin_mut, stream, StreamExt};
+
+        let (chunks, received) = std::sync::mpsc::channel();
+        let (sender, receiver) = mpsc::unbounded();
+
+        let decode = unblock(move || {
+            let mut chunks = Chunks::new(received);
+
+            // Just enough to tell the format apart
+            let mut magic = vec![];
+            io::Read::read_to_end(&mut io::Read::take(&mut chunks, 16), &mut magic)
+                .map_err(Error::Read)?;
+
+            check_gif(&magic)?;
+
+            let reader = io::Read::chain(io::Cursor::new(magic), chunks);
+
+            Self::decode_progressively(reader, |frames| {
+                let _ = sender.unbounded_send(Ok(frames));
+            })
+        });
+
+        // Dropping `chunks` once done ends the bytes being decoded
+        let read = async move {
+            pin_mut!(reader);
+
+            let mut buffer = vec![0; 8 * 1024];
+
+            loop {
+                match reader.read(&mut buffer).await {
+                    Ok(0) => return Ok(()),
+                    Ok(read) => {
+                        // Decoding already gave up
+                        if chunks.send(buffer[..read].to_vec()).is_err() {
+                            return Ok(());
+                        }
+                    }
+                    Err(error) => return Err(Error::Read(error)),
+                }
+            }
+        };
+
+        // A read error wins over the decode error of the bytes it
+        // cut short
+        let done = async move {
+            let (read, decode) = future::join(read, decode).await;
+
+            read.and(decode).err()
+        };
+
+        let error = stream::once(done).filter_map(|error| future::ready(error.map(Err)));
+
+        Task::run(stream::select(receiver, error), std::convert::identity)
+    }
+
+    /// Load [`Frames`] from the supplied async reader, reporting
+    /// [`Loading::Progress`] while reading
+    ///
+    /// The last item produced is always [`Loading::Done`].
+    pub fn load_from_reader_with_progress<R>(reader: R, total: Option<u64>) -> Task<Loading>
+    where
+        R: AsyncRead + MaybeSend + 'static,
+    {
+        use iced_futures::futures::{pin_mut, SinkExt};
+
+        let stream = iced_futures::stream::channel(1, move |mut sender| async move {
+            pin_mut!(reader);
+
+            let mut bytes = vec![];
+            let mut buffer = vec![0; 8 * 1024];
+
+            let result = loop {
+                match reader.read(&mut buffer).await {
+                    Ok(0) => break decode_in_background(bytes).await,
+                    Ok(read) => {
+                        bytes.extend_from_slice(&buffer[..read]);
+
+                        let _ = sender
+                            .send(Loading::Progress {
+                                read: bytes.len() as u64,
+                                total,
+                            })
+                            .await;
+                    }
+                    Err(error) => break Err(Error::Read(error)),
+                }
+            };
+
+            let _ = sender.send(Loading::Done(result)).await;
+        });
+
+        Task::run(stream, std::convert::identity)
+    }
+}
+
+impl Frames {
+    /// Decode [`Frames`] from the supplied bytes
+    ///
+    /// There's no HTTP client in this crate, so to load a remote gif
+    /// fetch its bytes with your own and decode them in the same task:
+    ///
+    /// ```ignore
+    /// Task::perform(
+    ///     async move {
+    ///         let response = reqwest::get(url).await?;
+    ///         let bytes = response.bytes().await?;
+    ///
+    ///         Ok(Frames::from_bytes(bytes.to_vec())?)
+    ///     },
+    ///     Message::Loaded,
+    /// )
+    /// ```
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, Error> {
+        Self::from_slice(&bytes)
+    }
+
+    /// Decode [`Frames`] from the supplied static bytes, such as
+    /// those embedded with [`include_bytes!`]
+    ///
+    /// The bytes are decoded in place, without copying them first.
+    pub fn from_static(bytes: &'static [u8]) -> Result<Self, Error> {
+        Self::from_slice(bytes)
+    }
+
+    /// Decode [`Frames`] from the supplied shared bytes, e.g. a body
+    /// from a networking stack
+    ///
+    /// The bytes are decoded in place, without copying them first.
+    #[cfg(feature = "bytes")]
+    pub fn from_bytes_shared(bytes: bytes::Bytes) -> Result<Self, Error> {
+        Self::from_slice(&bytes)
+    }
+
+    fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        Self::from_slice_until(bytes, &AtomicBool::new(false))
+    }
+
+    /// Decodes like [`Frames::from_slice`], but stops between frames
+    /// once `cancelled` is set
+    fn from_slice_until(bytes: &[u8], cancelled: &AtomicBool) -> Result<Self, Error> {
+        check_gif(bytes)?;
+
+        let decoder = gif_decoder(io::Cursor::new(bytes))?;
+
+        let loop_count = read_loop_count(bytes);
+
+        // `image` composites each frame onto a full-canvas buffer
+        // according to its disposal method, so every decoded frame
+        // is already a standalone image. Interlaced frames are
+        // de-interlaced by `gif` as they're read.
+        let frames = decoder.into_frames().map(|frame| {
+            // Nothing sees this error, as the decode is abandoned
+            if cancelled.load(atomic::Ordering::Relaxed) {
+                Err(Error::BlockingTask)
+            } else {
+                frame.map_err(Error::from)
+            }
+        });
+
+        let mut frames = Self::from_frames(frames, loop_count)?;
+
+        frames.warnings = read_warnings(bytes);
+
+        Ok(frames)
+    }
+
+    /// Decode only the first frame of the supplied bytes as a still
+    /// image [`Handle`]
+    ///
+    /// This is much cheaper than decoding every frame when only a
+    /// preview is needed, e.g. until a list item is hovered.
+    pub fn poster_from_bytes(bytes: Vec<u8>) -> Result<Handle, Error> {
+        check_gif(&bytes)?;
+
+        let decoder = gif_decoder(io::Cursor::new(&bytes))?;
+
+        let frame = decoder.into_frames().next().ok_or(Error::NoFrames)??;
+
+        Ok(Frame::from(frame).handle)
+    }
+
+    /// Decode [`Frames`] from the supplied bytes, producing the frames
+    /// decoded so far as decoding progresses
+    ///
+    /// The first frame is produced as soon as it's decoded, followed by
+    /// ever larger batches, and finally every frame. A [`Gif`] keeps
+    /// playing across these partial [`Frames`], so swapping each one in
+    /// animates whatever has decoded so far. Partial frames loop forever,
+    /// only the last has the declared [`LoopCount`].
+    pub fn stream_from_bytes(bytes: Vec<u8>) -> Task<Result<Frames, Error>> {
+        use iced_futures::futures::channel::mpsc;
+        use iced_futures::futures::{future, stream, StreamExt};
+
+        let (sender, receiver) = mpsc::unbounded();
+
+        let decode = unblock(move || {
+            check_gif(&bytes)?;
+
+            Self::decode_progressively(io::Cursor::new(&bytes), |frames| {
+                let _ = sender.unbounded_send(Ok(frames));
+            })
+        });
+
+        // Errors end the stream after whatever was already produced
+        let error = stream::once(decode).filter_map(|result| future::ready(result.err().map(Err)));
+
+        Task::run(stream::select(receiver, error), std::convert::identity)
+    }
+
+    /// Decodes the gif read from `reader`, producing the frames
+    /// decoded so far in ever larger batches
+    ///
+    /// Frames decode as soon as `reader` has produced their bytes.
+    fn decode_progressively(
+        reader: impl io::Read,
+        mut produce: impl FnMut(Frames),
+    ) -> Result<(), Error> {
+        let mut decoder = lazy::Decoder::new(reader)?;
+
+        // Every partial `Frames` shares the hash of the first frame
+        // so the widget doesn't reset its playback for each
+        let partial = |frames: &[Frame], loop_count| {
+            let mut partial = Self::new(
+                frames[0].clone(),
+                Source::Decoded(frames.to_vec()),
+                loop_count,
+            );
+            partial.hash = content_hash(&frames[0], []);
+            partial
+        };
+
+        let mut frames = vec![];
+        let mut batch = 1;
+
+        while let Some(frame) = decoder.next_frame()? {
+            frames.push(frame);
+
+            if frames.len() == batch {
+                produce(partial(&frames, LoopCount::Infinite));
+
+                batch *= 2;
+            }
+        }
+
+        if frames.is_empty() {
+            return Err(Error::NoFrames);
+        }
+
+        // The loop count may come after the first frame
+        produce(partial(&frames, decoder.loop_count()));
+
+        Ok(())
+    }
+
+    /// Decode only the first `max_frames` [`Frames`] from the supplied bytes
+    ///
+    /// Decoding stops once `max_frames` have been read, which makes quick
+    /// previews of long gifs much cheaper. The truncated frames loop
+    /// the same way as the whole gif.
+    pub fn from_bytes_truncated(bytes: Vec<u8>, max_frames: usize) -> Result<Self, Error> {
+        check_gif(&bytes)?;
+
+        let decoder = gif_decoder(io::Cursor::new(&bytes))?;
+
+        let loop_count = read_loop_count(&bytes);
+
+        Self::from_frames(decoder.into_frames().take(max_frames), loop_count)
+    }
+
+    /// Decode [`Frames`] from the supplied bytes, treating every pixel
+    /// of the `key` color as fully transparent
+    ///
+    /// This fixes gifs whose transparent color index is decoded as an
+    /// opaque background.
+    pub fn from_bytes_with_transparency(
+        bytes: Vec<u8>,
+        key: Option<image_rs::Rgb<u8>>,
+    ) -> Result<Self, Error> {
+        check_gif(&bytes)?;
+
+        let decoder = gif_decoder(io::Cursor::new(&bytes))?;
+
+        let loop_count = read_loop_count(&bytes);
+
+        let frames = decoder.into_frames().map(|result| {
+            result.map(|mut frame| {
+                if let Some(key) = key {
+                    for pixel in frame.buffer_mut().pixels_mut() {
+                        if pixel.0[..3] == key.0 {
+                            pixel.0[3] = 0;
+                        }
+                    }
+                }
+
+                frame
+            })
+        });
+
+        Self::from_frames(frames, loop_count)
+    }
+
+    /// Decode [`Frames`] from the supplied bytes, failing with
+    /// [`Error::LimitExceeded`] if any of the given limits are exceeded
+    pub fn from_bytes_limited(bytes: Vec<u8>, limits: DecodeLimits) -> Result<Self, Error> {
+        let limit_exceeded = |error| match error {
+            image_rs::ImageError::Limits(_) => Error::LimitExceeded,
+            error => Error::Image(error),
+        };
+
+        check_gif(&bytes)?;
+
+        let mut image_limits = image_rs::io::Limits::default();
+        image_limits.max_image_width = Some(limits.max_width);
+        image_limits.max_image_height = Some(limits.max_height);
+        image_limits.max_alloc = Some(limits.max_total_bytes);
+
+        let mut decoder = gif_decoder(io::Cursor::new(&bytes))?;
+        decoder.set_limits(image_limits).map_err(limit_exceeded)?;
+
+        let (width, height) = decoder.dimensions();
+        let frame_bytes = u64::from(width) * u64::from(height) * 4;
+
+        let loop_count = read_loop_count(&bytes);
+
+        let mut total_bytes = 0u64;
+
+        let frames = decoder.into_frames().enumerate().map(|(index, result)| {
+            total_bytes = total_bytes.saturating_add(frame_bytes);
+
+            if index >= limits.max_frames || total_bytes > limits.max_total_bytes {
+                return Err(Error::LimitExceeded);
+            }
+
+            result.map_err(limit_exceeded)
+        });
+
+        Self::from_frames(frames, loop_count)
+    }
+
+    /// Decode [`Frames`] from the supplied bytes, enforcing the given
+    /// `image` [`Limits`](image_rs::io::Limits) while decoding
+    ///
+    /// Exceeding a limit fails with an [`Error::Image`].
+    pub fn from_bytes_with_limits(
+        bytes: Vec<u8>,
+        limits: image_rs::io::Limits,
+    ) -> Result<Self, Error> {
+        check_gif(&bytes)?;
+
+        let mut decoder = gif_decoder(io::Cursor::new(&bytes))?;
+        decoder.set_limits(limits)?;
+
+        let loop_count = read_loop_count(&bytes);
+
+        Self::from_frames(decoder.into_frames(), loop_count)
+    }
+
+    /// Create [`Frames`] from the supplied bytes that decode each
+    /// frame as playback reaches it
+    ///
+    /// Only a small window of decoded frames is kept in memory, trading
+    /// CPU for memory on long or high resolution gifs. Frames that fail
+    /// to decode display the first frame instead.
+    pub fn from_bytes_lazy(bytes: Vec<u8>) -> Result<Self, Error> {
+        check_gif(&bytes)?;
+
+        let (lazy, first, loop_count) = lazy::Lazy::new(bytes)?;
+
+        Ok(Self::new(first, Source::Lazy(Box::new(lazy)), loop_count))
+    }
+
+    /// Decode [`Frames`] from the supplied animated WebP bytes
+    ///
+    /// The WebP loop count isn't exposed by the decoder, so these
+    /// frames always loop forever. A still WebP decodes to a single frame.
+    ///
+    /// Needs the `webp` feature.
+    #[cfg(feature = "webp")]
+    pub fn from_webp_bytes(bytes: Vec<u8>) -> Result<Self, Error> {
+        let decoder = webp::WebPDecoder::new(io::Cursor::new(bytes))?;
+
+        if decoder.has_animation() {
+            Self::from_frames(decoder.into_frames(), LoopCount::Infinite)
+        } else {
+            Self::from_still(image_rs::DynamicImage::from_decoder(decoder)?)
+        }
+    }
+
+    /// Decode [`Frames`] from the supplied APNG bytes
+    ///
+    /// The APNG play count isn't exposed by the decoder, so these
+    /// frames always loop forever. A still PNG decodes to a single frame.
+    ///
+    /// Needs the `apng` feature.
+    #[cfg(feature = "apng")]
+    pub fn from_apng_bytes(bytes: Vec<u8>) -> Result<Self, Error> {
+        let decoder = png::PngDecoder::new(io::Cursor::new(bytes))?;
+
+        if decoder.is_apng() {
+            // `image` keeps a canvas and applies each frame's blend and
+            // dispose ops, so every frame is already a full snapshot
+            Self::from_frames(decoder.apng().into_frames(), LoopCount::Infinite)
+        } else {
+            Self::from_still(image_rs::DynamicImage::from_decoder(decoder)?)
+        }
+    }
+
+    /// Decode [`Frames`] from the supplied gif, WebP or APNG bytes,
+    /// detecting the format from its magic bytes
+    ///
+    /// Bytes of any other format, or of no known format at all, fail
+    /// with [`Error::UnsupportedFormat`]. WebP and APNG are only
+    /// supported with the `webp` and `apng` features.
+    pub fn from_bytes_auto(bytes: Vec<u8>) -> Result<Self, Error> {
+        match image_rs::guess_format(&bytes) {
+            Ok(image_rs::ImageFormat::Gif) => Self::from_bytes(bytes),
+            #[cfg(feature = "webp")]
+            Ok(image_rs::ImageFormat::WebP) => Self::from_webp_bytes(bytes),
+            #[cfg(feature = "apng")]
+            Ok(image_rs::ImageFormat::Png) => Self::from_apng_bytes(bytes),
+            detected => Err(Error::UnsupportedFormat {
+                detected: detected.ok(),
+            }),
+        }
+    }
+
+    /// Create [`Frames`] from already decoded `image` frames
+    ///
+    /// These frames always loop forever.
+    pub fn from_image_frames(frames: image_rs::Frames<'_>) -> Result<Self, Error> {
+        Self::from_frames(frames, LoopCount::Infinite)
+    }
+
+    fn new(first: Frame, source: Source, loop_count: LoopCount) -> Self {
+        let delays: Vec<_> = match &source {
+            Source::Decoded(frames) => frames.iter().map(|frame| frame.delay).collect(),
+            Source::Lazy(lazy) => (0..lazy.len()).map(|index| lazy.delay(index)).collect(),
+        };
+
+        let ends = delays
+            .iter()
+            .scan(Duration::ZERO, |end, delay| {
+                *end += *delay;
+                Some(*end)
+            })
+            .collect();
+
+        Frames {
+            hash: content_hash(&first, delays),
+            ends,
+            first,
+            source,
+            loop_count,
+            warnings: vec![],
+            flipped: Arc::default(),
+        }
+    }
+
+    #[cfg(any(feature = "webp", feature = "apng"))]
+    fn from_still(image: image_rs::DynamicImage) -> Result<Self, Error> {
+        let frame = image_rs::Frame::new(image.into_rgba8());
+
+        Self::from_frames([Ok::<_, Error>(frame)], LoopCount::Infinite)
+    }
+
+    fn from_frames<E: Into<Error>>(
+        frames: impl IntoIterator<Item = Result<image_rs::Frame, E>>,
+        loop_count: LoopCount,
+    ) -> Result<Self, Error> {
+        let frames = frames
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(Into::into)?;
+
+        let frames: Vec<_> = fill_canvas(frames).into_iter().map(Frame::from).collect();
+
+        let first = frames.first().cloned().ok_or(Error::NoFrames)?;
+
+        Ok(Self::new(first, Source::Decoded(frames), loop_count))
+    }
+
+    /// Returns the [`LoopCount`] declared by the gif
+    pub fn loop_count(&self) -> LoopCount {
+        self.loop_count
+    }
+
+    /// Returns the number of frames
+    pub fn len(&self) -> usize {
+        match &self.source {
+            Source::Decoded(frames) => frames.len(),
+            Source::Lazy(lazy) => lazy.len(),
+        }
+    }
+
+    /// Returns `true` if there are no frames
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the non-fatal issues found decoding the gif
+    ///
+    /// These are only collected by [`Frames::from_bytes`] and the
+    /// loaders built on it.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Returns the width and height of the frames, in pixels
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.first.dimensions()
+    }
+
+    /// Returns the logical screen size declared by the gif
+    ///
+    /// Every frame is composited onto a canvas of this size, so it
+    /// matches [`Frames::dimensions`] even when the gif encodes
+    /// smaller sub-frames.
+    pub fn logical_size(&self) -> Size<u32> {
+        let (width, height) = self.dimensions();
+
+        Size::new(width, height)
+    }
+
+    /// Returns the size of every frame decoded to RGBA, in bytes
+    ///
+    /// This is cheap to compute and a handy signal that frames have
+    /// changed, e.g. for custom caching. It's a heuristic rather than
+    /// a unique id, as different gifs can have the same size; compare
+    /// the [`Frames`] themselves to tell them apart by content. Lazily
+    /// decoded frames count as if they were all decoded.
+    pub fn total_bytes(&self) -> u64 {
+        let (width, height) = self.dimensions();
+
+        u64::from(width) * u64::from(height) * 4 * self.len() as u64
+    }
+
+    /// Returns the duration of a single loop of the animation
+    pub fn total_duration(&self) -> Duration {
+        self.ends.last().copied().unwrap_or_default()
+    }
+
+    /// Returns the average number of frames shown per second
+    ///
+    /// Frames without any delay yield `0.0`.
+    pub fn average_fps(&self) -> f32 {
+        let seconds = self.total_duration().as_secs_f32();
+
+        if seconds > 0.0 {
+            self.len() as f32 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the index of the frame shown at the given time into
+    /// the animation
+    ///
+    /// Times past the [`Frames::total_duration`] wrap around. This uses
+    /// the decoded delays, ignoring any playback speed.
+    pub fn index_at(&self, time: Duration) -> usize {
+        let total = self.total_duration().as_nanos();
+
+        if total == 0 {
+            return 0;
+        }
+
+        let time = time.as_nanos() % total;
+
+        self.ends
+            .partition_point(|end| end.as_nanos() <= time)
+            .min(self.len().saturating_sub(1))
+    }
+
+    /// Returns the delay of every frame
+    pub fn frame_delays(&self) -> Vec<Duration> {
+        (0..self.len()).map(|index| self.delay(index)).collect()
+    }
+
+    /// Returns the image [`Handle`] of the frame at the given index
+    pub fn frame_handle(&self, index: usize) -> Option<Handle> {
+        (index < self.len()).then(|| self.frame(index).handle)
+    }
+
+    /// Returns the frame at the given index as a still image [`Handle`]
+    ///
+    /// This is an alias of [`Frames::frame_handle`].
+    pub fn still_frame(&self, index: usize) -> Option<Handle> {
+        self.frame_handle(index)
+    }
+
+    /// Returns the width, height and raw RGBA pixels of the frame at
+    /// the given index
+    ///
+    /// The pixels are copied out of the frame's [`Handle`], so no
+    /// memory is held on to for this. Feed them back into `image` to
+    /// encode the frame in any still format.
+    ///
+    /// The alpha is straight, not premultiplied. Fully transparent
+    /// pixels take the color of an opaque neighbor, so they don't draw
+    /// dark halos when scaled, and won't read back as the color the
+    /// gif gave them.
+    pub fn frame_rgba(&self, index: usize) -> Option<(u32, u32, Vec<u8>)> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let rgba = self.frame(index).rgba()?;
+
+        Some((rgba.width(), rgba.height(), rgba.into_raw()))
+    }
+
+    /// Save the frame at the given index to the supplied path,
+    /// encoded in the given format
+    ///
+    /// Fails with [`Error::NoFrames`] if there's no frame at the index.
+    /// The pixels saved are those of [`Frames::frame_rgba`], with any
+    /// fully transparent pixels colored like their neighbors.
+    pub fn save_frame(
+        &self,
+        index: usize,
+        path: impl AsRef<Path>,
+        format: image_rs::ImageFormat,
+    ) -> Task<Result<(), Error>> {
+        let path = path.as_ref().to_path_buf();
+        let rgba = self.frame_rgba(index);
+
+        let f = async move {
+            let (width, height, pixels) = rgba.ok_or(Error::NoFrames)?;
+
+            unblock(move || {
+                image_rs::save_buffer_with_format(
+                    path,
+                    &pixels,
+                    width,
+                    height,
+                    image_rs::ColorType::Rgba8,
+                    format,
+                )
+                .map_err(Error::from)
+            })
+            .await
+        };
+
+        Task::perform(f, std::convert::identity)
+    }
+
+    /// Returns an iterator over the delay and image [`Handle`] of
+    /// every frame
+    pub fn iter_frames(&self) -> impl Iterator<Item = (Duration, Handle)> + '_ {
+        (0..self.len()).map(|index| {
+            let frame = self.frame(index);
+
+            (frame.delay, frame.handle)
+        })
+    }
+
+    /// Returns a copy of these frames with the RGB of every pixel
+    /// multiplied by the given tint [`Color`]
+    ///
+    /// Per-pixel alpha is kept as is, so a grayscale gif can be
+    /// recolored to match a theme. Lazily decoded frames are all
+    /// decoded up front.
+    pub fn tinted(&self, tint: Color) -> Frames {
+        let Color { r, g, b, .. } = tint;
+
+        self.map_pixels(|mut rgba| {
+            for pixel in rgba.pixels_mut() {
+                for (channel, tint) in pixel.0.iter_mut().zip([r, g, b]) {
+                    *channel = (f32::from(*channel) * tint).round() as u8;
+                }
+            }
+
+            rgba
+        })
+    }
+
+    /// Returns a desaturated copy of these frames, e.g. to show a
+    /// disabled item
+    ///
+    /// Alpha is kept as is. Lazily decoded frames are all decoded up
+    /// front.
+    pub fn grayscale(&self) -> Frames {
+        self.map_pixels(|mut rgba| {
+            for pixel in rgba.pixels_mut() {
+                let [r, g, b, _] = pixel.0.map(f32::from);
+                let luma = (0.2126 * r + 0.7152 * g + 0.0722 * b).round() as u8;
+
+                pixel.0[..3].fill(luma);
+            }
+
+            rgba
+        })
+    }
+
+    /// Joins the frames of every part, in order, into one animation
+    ///
+    /// Parts smaller than the largest are centered on a transparent
+    /// canvas of its size. The joined frames loop forever.
+    pub fn concat(parts: Vec<Frames>) -> Frames {
+        let (width, height) = parts
+            .iter()
+            .filter(|part| !part.is_empty())
+            .map(Frames::dimensions)
+            .fold((0, 0), |(width, height), (w, h)| {
+                (width.max(w), height.max(h))
+            });
+
+        let frames: Vec<_> = parts
+            .iter()
+            .flat_map(|part| (0..part.len()).map(|index| part.frame(index)))
+            .map(|frame| match frame.rgba() {
+                Some(rgba) if rgba.dimensions() != (width, height) => {
+                    let mut canvas = image_rs::RgbaImage::new(width, height);
+
+                    image_rs::imageops::replace(
+                        &mut canvas,
+                        &rgba,
+                        i64::from((width - rgba.width()) / 2),
+                        i64::from((height - rgba.height()) / 2),
+                    );
+
+                    Frame {
+                        delay: frame.delay,
+                        handle: Handle::from_rgba(width, height, canvas.into_raw()),
+                    }
+                }
+                _ => frame,
+            })
+            .collect();
+
+        match frames.first().cloned() {
+            Some(first) => Self::new(first, Source::Decoded(frames), LoopCount::Infinite),
+            None => Self::empty(),
+        }
+    }
+
+    /// Returns a copy of these frames composited over the given
+    /// background [`Color`]
+    ///
+    /// This gives partially transparent gifs the same appearance on any
+    /// background, without drawing the color each frame. Lazily decoded
+    /// frames are all decoded up front.
+    pub fn on_background(&self, background: Color) -> Frames {
+        let [r, g, b, a] = background.into_rgba8();
+
+        self.map_pixels(|mut rgba| {
+            for pixel in rgba.pixels_mut() {
+                let alpha = f32::from(pixel.0[3]) / 255.0;
+
+                let blend = |top: u8, bottom: u8| {
+                    (f32::from(top) * alpha + f32::from(bottom) * (1.0 - alpha)).round() as u8
+                };
+
+                pixel.0 = [
+                    blend(pixel.0[0], r),
+                    blend(pixel.0[1], g),
+                    blend(pixel.0[2], b),
+                    blend(255, a),
+                ];
+            }
+
+            rgba
+        })
+    }
+
+    /// Returns a copy of these frames resized to the given width and
+    /// height, in pixels
+    ///
+    /// Unlike [`Gif::thumbnail`], the resized frames can be shared by
+    /// any number of widgets. The aspect ratio isn't kept. Lazily
+    /// decoded frames are all decoded up front.
+    pub fn resized(
+        &self,
+        width: u32,
+        height: u32,
+        filter: image_rs::imageops::FilterType,
+    ) -> Frames {
+        self.map_pixels(|rgba| image_rs::imageops::resize(&rgba, width, height, filter))
+    }
+
+    /// Returns a copy of these frames in reverse order
+    ///
+    /// Each frame keeps its delay, and the frame images are shared,
+    /// not copied. Lazily decoded frames are all decoded up front.
+    pub fn reversed(&self) -> Frames {
+        let frames: Vec<_> = (0..self.len())
+            .rev()
+            .map(|index| self.frame(index))
+            .collect();
+
+        let first = frames
+            .first()
+            .cloned()
+            .unwrap_or_else(|| self.first.clone());
+
+        Self::new(first, Source::Decoded(frames), self.loop_count)
+    }
+
+    /// Returns a copy of these frames mirrored left to right and/or
+    /// top to bottom
+    ///
+    /// The copy is made once and kept with these frames, so every
+    /// clone flipped the same way shares it, as do all the widgets
+    /// drawing them with [`Gif::flip_horizontal`] or
+    /// [`Gif::flip_vertical`]. Lazily decoded frames are all decoded
+    /// up front.
+    pub fn flipped(&self, horizontal: bool, vertical: bool) -> Frames {
+        if !horizontal && !vertical {
+            return self.clone();
+        }
+
+        let Ok(mut copies) = self.flipped.lock() else {
+            return self.flip(horizontal, vertical);
+        };
+
+        let slot = usize::from(horizontal) + usize::from(vertical) * 2 - 1;
+
+        copies[slot]
+            .get_or_insert_with(|| self.flip(horizontal, vertical))
+            .clone()
+    }
+
+    fn flip(&self, horizontal: bool, vertical: bool) -> Frames {
+        self.map_pixels(|mut rgba| {
+            if horizontal {
+                image_rs::imageops::flip_horizontal_in_place(&mut rgba);
+            }
+            if vertical {
+                image_rs::imageops::flip_vertical_in_place(&mut rgba);
+            }
+
+            rgba
+        })
+    }
+
+    /// Returns a copy of these frames with every delay divided by
+    /// `factor`
+    ///
+    /// `2.0` plays twice as fast, `0.5` at half speed. Values at or
+    /// below zero are clamped to a tiny positive value. The frame
+    /// images are shared, not copied.
+    pub fn with_speed(&self, factor: f32) -> Frames {
+        let factor = factor.max(f32::EPSILON);
+
+        let source = match &self.source {
+            Source::Decoded(frames) => Source::Decoded(
+                frames
+                    .iter()
+                    .map(|frame| Frame {
+                        delay: frame.delay.div_f32(factor),
+                        handle: frame.handle.clone(),
+                    })
+                    .collect(),
+            ),
+            Source::Lazy(lazy) => Source::Lazy(Box::new(lazy.with_speed(factor))),
+        };
+
+        let first = Frame {
+            delay: self.first.delay.div_f32(factor),
+            handle: self.first.handle.clone(),
+        };
+
+        Self::new(first, source, self.loop_count)
+    }
+
+    /// Returns a copy of these frames cropped to the given region, in
+    /// pixels
+    ///
+    /// This shows only part of each frame, e.g. one sprite of a sheet
+    /// or a region of interest. The region is clamped to the frames,
+    /// keeping at least one pixel. Lazily decoded frames are all
+    /// decoded up front.
+    pub fn cropped(&self, region: Rectangle<u32>) -> Frames {
+        self.map_pixels(|rgba| {
+            let x = region.x.min(rgba.width().saturating_sub(1));
+            let y = region.y.min(rgba.height().saturating_sub(1));
+
+            image_rs::imageops::crop_imm(&rgba, x, y, region.width.max(1), region.height.max(1))
+                .to_image()
+        })
+    }
+
+    /// Returns a copy of these frames with `f` applied to the pixels
+    /// of every frame
+    fn map_pixels(&self, f: impl Fn(image_rs::RgbaImage) -> image_rs::RgbaImage) -> Frames {
+        let frames: Vec<_> = (0..self.len())
+            .map(|index| {
+                let frame = self.frame(index);
+
+                match frame.rgba() {
+                    Some(rgba) => {
+                        let rgba = f(rgba);
+
+                        Frame {
+                            delay: frame.delay,
+                            handle: Handle::from_rgba(rgba.width(), rgba.height(), rgba.into_raw()),
+                        }
+                    }
+                    None => frame,
+                }
+            })
+            .collect();
+
+        let first = frames
+            .first()
+            .cloned()
+            .unwrap_or_else(|| self.first.clone());
+
+        Self::new(first, Source::Decoded(frames), self.loop_count)
+    }
+
+    /// Returns the first frame as a still image [`Handle`]
+    pub fn poster(&self) -> Handle {
+        self.first.handle.clone()
+    }
+
+    /// How far through a loop the frame at `index` starts, from
+    /// `0.0` to `1.0`, when playing in `direction`
+    fn progress(&self, index: usize, direction: Direction) -> f32 {
+        let total = self.total_duration().as_secs_f32();
+
+        if total == 0.0 {
+            return 0.0;
+        }
+
+        let elapsed = match direction {
+            Direction::Forward => index
+                .checked_sub(1)
+                .and_then(|previous| self.ends.get(previous))
+                .copied()
+                .unwrap_or_default(),
+            Direction::Backward => self
+                .total_duration()
+                .saturating_sub(self.ends.get(index).copied().unwrap_or_default()),
+        };
+
+        (elapsed.as_secs_f32() / total).clamp(0.0, 1.0)
+    }
+
+    fn frame(&self, index: usize) -> Frame {
+        match &self.source {
+            Source::Decoded(frames) => frames
+                .get(index)
+                .cloned()
+                .unwrap_or_else(|| self.first.clone()),
+            Source::Lazy(lazy) => lazy.frame(index).unwrap_or_else(|| self.first.clone()),
+        }
+    }
+
+    fn delay(&self, index: usize) -> Duration {
+        match &self.source {
+            Source::Decoded(frames) => frames
+                .get(index)
+                .map_or(Duration::ZERO, |frame| frame.delay),
+            Source::Lazy(lazy) => lazy.delay(index),
+        }
+    }
+}
+
+/// Draws any frame that only covers part of the canvas at its
+/// offset over the frame before it
+///
+/// Transparent pixels of a frame let the frame before show through.
+///
+/// `image` already composites gif, APNG and WebP frames onto the full
+/// canvas, but frames built by hand for [`Frames::from_image_frames`]
+/// may be sub-rects placed at an offset.
+fn fill_canvas(frames: Vec<image_rs::Frame>) -> Vec<image_rs::Frame> {
+    let (width, height) = frames.iter().fold((0, 0), |(width, height), frame| {
+        (
+            width.max(frame.left() + frame.buffer().width()),
+            height.max(frame.top() + frame.buffer().height()),
+        )
+    });
+
+    let is_full = |frame: &image_rs::Frame| {
+        frame.left() == 0 && frame.top() == 0 && frame.buffer().dimensions() == (width, height)
+    };
+
+    if frames.iter().all(is_full) {
+        return frames;
+    }
+
+    let mut canvas = image_rs::RgbaImage::new(width, height);
+
+    frames
+        .into_iter()
+        .map(|frame| {
+            image_rs::imageops::overlay(
+                &mut canvas,
+                frame.buffer(),
+                frame.left().into(),
+                frame.top().into(),
+            );
+
+            image_rs::Frame::from_parts(canvas.clone(), 0, 0, frame.delay())
+        })
+        .collect()
+}
+
+/// Hashes the first frame and every frame delay, so different
+/// gifs can be told apart without comparing all their pixels
+fn content_hash(first: &Frame, delays: impl IntoIterator<Item = Duration>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    first.dimensions().hash(&mut hasher);
+
+    if let image::Handle::Rgba { pixels, .. } = &first.handle {
+        pixels.hash(&mut hasher);
+    }
+
+    for delay in delays {
+        delay.hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
+
+/// Runs the CPU-bound `decode` on a background thread so it
+/// doesn't stall the executor polling it
+async fn unblock<T>(decode: impl FnOnce() -> Result<T, Error> + Send + 'static) -> Result<T, Error>
+where
+    T: Send + 'static,
+{
+    #[cfg(feature = "tokio")]
+    {
+        tokio::task::spawn_blocking(decode)
+            .await
+            .map_err(|_| Error::BlockingTask)?
+    }
+    #[cfg(all(not(feature = "tokio"), feature = "blocking"))]
+    {
+        blocking::unblock(decode).await
+    }
+    #[cfg(all(not(feature = "tokio"), not(feature = "blocking")))]
+    {
+        decode()
+    }
+}
+
+/// Reads the chunks sent by [`Frames::stream_from_reader`] as they
+/// arrive, ending once they stop being sent
+#[cfg(any(feature = "async-fs", feature = "tokio"))]
+struct Chunks {
+    received: std::sync::mpsc::Receiver<Vec<u8>>,
+    chunk: io::Cursor<Vec<u8>>,
+}
+
+#[cfg(any(feature = "async-fs", feature = "tokio"))]
+impl Chunks {
+    fn new(received: std::sync::mpsc::Receiver<Vec<u8>>) -> Self {
+        Self {
+            received,
+            chunk: io::Cursor::default(),
+        }
+    }
+}
+
+#[cfg(any(feature = "async-fs", feature = "tokio"))]
+impl io::Read for Chunks {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = io::Read::read(&mut self.chunk, buffer)?;
+
+            if read > 0 || buffer.is_empty() {
+                return Ok(read);
+            }
+
+            // Blocks until the next chunk is read
+            match self.received.recv() {
+                Ok(chunk) => self.chunk = io::Cursor::new(chunk),
+                Err(_) => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Decodes read `bytes` with [`unblock`], stopping the decode once
+/// the returned future is dropped, e.g. by an aborted [`Task`]
+#[cfg(any(feature = "async-fs", feature = "tokio"))]
+async fn decode_in_background(bytes: Vec<u8>) -> Result<Frames, Error> {
+    struct CancelOnDrop(Arc<AtomicBool>);
+
+    impl Drop for CancelOnDrop {
+        fn drop(&mut self) {
+            self.0.store(true, atomic::Ordering::Relaxed);
+        }
+    }
+
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let _guard = CancelOnDrop(cancelled.clone());
+
+    unblock(move || Frames::from_slice_until(&bytes, &cancelled))
+        .await
+        .map_err(Error::into_decode)
+}
+
+/// Decoded [`Frames`] shared by [`Frames::load_from_path_cached`]
+#[cfg(any(feature = "async-fs", feature = "tokio"))]
+struct Cache {
+    capacity: usize,
+    entries: HashMap<PathBuf, (Frames, u64)>,
+    /// Bumped on every use, to find the least recently used entry
+    clock: u64,
+}
+
+#[cfg(any(feature = "async-fs", feature = "tokio"))]
+impl Cache {
+    fn get(&mut self, path: &Path) -> Option<Frames> {
+        self.clock += 1;
+
+        let (frames, used) = self.entries.get_mut(path)?;
+        *used = self.clock;
+
+        Some(frames.clone())
+    }
+
+    fn insert(&mut self, path: PathBuf, frames: Frames) {
+        self.clock += 1;
+
+        self.entries.insert(path, (frames, self.clock));
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.entries.len() > self.capacity {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(path, _)| path.clone())
+            else {
+                break;
+            };
+
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(any(feature = "async-fs", feature = "tokio"))]
+fn cache() -> std::sync::MutexGuard<'static, Cache> {
+    static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+
+    CACHE
+        .get_or_init(|| {
+            Mutex::new(Cache {
+                capacity: 64,
+                entries: HashMap::new(),
+                clock: 0,
+            })
+        })
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Scans the gif for issues that don't stop it from decoding
+fn read_warnings(bytes: &[u8]) -> Vec<String> {
+    let mut options = gif_rs::DecodeOptions::new();
+    options.skip_frame_decoding(true);
+
+    let Ok(mut reader) = options.read_info(bytes) else {
+        return vec![];
+    };
+
+    let (width, height) = (reader.width(), reader.height());
+    let mut warnings = vec![];
+
+    for index in 0.. {
+        let frame = match reader.read_next_frame() {
+            Ok(Some(frame)) => frame,
+            Ok(None) => break,
+            Err(error) => {
+                warnings.push(format!("stopped reading after frame {index}: {error}"));
+                break;
+            }
+        };
+
+        if u32::from(frame.left) + u32::from(frame.width) > u32::from(width)
+            || u32::from(frame.top) + u32::from(frame.height) > u32::from(height)
+        {
+            warnings.push(format!(
+                "frame {index} extends past the {width}x{height} canvas and is cropped"
+            ));
+        }
+
+        if frame.delay == 0 {
+            warnings.push(format!("frame {index} has no delay"));
+        }
+    }
+
+    warnings
+}
+
+/// Waits for `duration` without stalling the executor
+#[cfg(any(feature = "async-fs", feature = "tokio"))]
+async fn sleep(duration: Duration) {
+    #[cfg(feature = "tokio")]
+    tokio::time::sleep(duration).await;
+
+    #[cfg(not(feature = "tokio"))]
+    let _ = async_io::Timer::after(duration).await;
+}
+
+/// Builds [`Frames`] from raw RGBA frames, e.g. drawn procedurally
+#[derive(Default)]
+pub struct FramesBuilder {
+    frames: Vec<image_rs::Frame>,
+    loop_count: LoopCount,
+}
+
+impl fmt::Debug for FramesBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FramesBuilder")
+            .field("frames", &self.frames.len())
+            .field("loop_count", &self.loop_count)
+            .finish()
+    }
+}
+
+impl FramesBuilder {
+    /// Creates an empty [`FramesBuilder`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a frame of the given RGBA pixels, shown for `delay`
+    ///
+    /// Frames smaller than the largest are drawn at the top left of
+    /// its canvas, over the frame before them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rgba` isn't `width * height * 4` bytes long.
+    pub fn push_frame(mut self, rgba: Vec<u8>, width: u32, height: u32, delay: Duration) -> Self {
+        let buffer = image_rs::RgbaImage::from_raw(width, height, rgba)
+            .expect("rgba should be width * height * 4 bytes");
+
+        self.frames.push(image_rs::Frame::from_parts(
+            buffer,
+            0,
+            0,
+            image_rs::Delay::from_saturating_duration(delay),
+        ));
+        self
+    }
+
+    /// Sets the [`LoopCount`] of the built [`Frames`].
+    ///
+    /// Defaults to [`LoopCount::Infinite`].
+    pub fn loop_count(mut self, loop_count: LoopCount) -> Self {
+        self.loop_count = loop_count;
+        self
+    }
+
+    /// Builds the [`Frames`], which are [`Frames::empty`] if
+    /// no frame was pushed
+    pub fn build(self) -> Frames {
+        Frames::from_frames(self.frames.into_iter().map(Ok::<_, Error>), self.loop_count)
+            .unwrap_or_default()
+    }
+}
+
+/// Fails with [`Error::UnsupportedFormat`] if the magic bytes
+/// are recognized as something other than a gif
+fn check_gif(bytes: &[u8]) -> Result<(), Error> {
+    match image_rs::guess_format(bytes) {
+        Ok(image_rs::ImageFormat::Gif) | Err(_) => Ok(()),
+        Ok(detected) => Err(Error::UnsupportedFormat {
+            detected: Some(detected),
+        }),
+    }
+}
+
+/// Creates a gif decoder reading from `reader`
+///
+/// A gif that ends before its first frame, e.g. one that's only a
+/// header, fails with [`Error::NoFrames`] instead of an unexpected end.
+fn gif_decoder<R: io::Read>(reader: R) -> Result<gif::GifDecoder<R>, Error> {
+    gif::GifDecoder::new(reader).map_err(|error| match error {
+        image_rs::ImageError::IoError(error) if error.kind() == io::ErrorKind::UnexpectedEof => {
+            Error::NoFrames
+        }
+        error => error.into(),
+    })
+}
+
+/// Reads the Netscape loop count from the gif header
+///
+/// `image` doesn't expose the extension, so it's read directly.
+fn read_loop_count(bytes: &[u8]) -> LoopCount {
+    gif_rs::DecodeOptions::new()
+        .read_info(bytes)
+        .map(|decoder| decoder.repeat().into())
+        .unwrap_or_default()
+}
+
+#[derive(Clone)]
+struct Frame {
+    delay: Duration,
+    handle: image::Handle,
+}
+
+impl Frame {
+    fn dimensions(&self) -> (u32, u32) {
+        match &self.handle {
+            image::Handle::Rgba { width, height, .. } => (*width, *height),
+            _ => (0, 0),
+        }
+    }
+
+    fn rgba(&self) -> Option<image_rs::RgbaImage> {
+        rgba(&self.handle)
+    }
+}
+
+/// Copies the pixels out of an RGBA `handle`
+fn rgba(handle: &Handle) -> Option<image_rs::RgbaImage> {
+    match handle {
+        image::Handle::Rgba {
+            width,
+            height,
+            pixels,
+            ..
+        } => image_rs::RgbaImage::from_raw(*width, *height, pixels.to_vec()),
+        _ => None,
+    }
+}
+
+/// Gives fully transparent pixels the color of an opaque neighbor
+///
+/// Renderers expect the straight alpha `image` decodes to, but linear
+/// filtering still blends edge pixels with their transparent neighbors.
+/// Those are black, which would leave dark halos around the edges of
+/// transparent gifs when scaled.
+fn bleed_edges(pixels: &mut [u8], width: u32) {
+    let width = width as usize * 4;
+
+    for index in (0..pixels.len()).step_by(4) {
+        if pixels[index + 3] != 0 {
+            continue;
+        }
+
+        let x = index % width;
+
+        let neighbors = [
+            (x >= 4).then(|| index - 4),
+            (x + 4 < width).then(|| index + 4),
+            index.checked_sub(width),
+            Some(index + width).filter(|below| *below < pixels.len()),
+        ];
+
+        // Bled pixels stay transparent, so they're never bled from
+        if let Some(neighbor) = neighbors
+            .into_iter()
+            .flatten()
+            .find(|neighbor| pixels[neighbor + 3] != 0)
+        {
+            pixels.copy_within(neighbor..neighbor + 3, index);
+        }
+    }
+}
+
+impl From<image_rs::Frame> for Frame {
+    fn from(frame: image_rs::Frame) -> Self {
+        let (width, height) = frame.buffer().dimensions();
+
+        let delay = frame.delay().into();
+
+        // An `image` frame is always 8-bit RGBA, so deeper WebP and
+        // APNG frames have already been converted down by the decoder
+        let mut pixels = frame.into_buffer().into_vec();
+        bleed_edges(&mut pixels, width);
+
+        let handle = image::Handle::from_rgba(width, height, pixels);
+
+        Self { delay, handle }
+    }
+}
+
+struct State {
+    index: usize,
+    /// The frame playback started from, which [`EndAction::HoldFirst`]
+    /// goes back to
+    start: usize,
+    len: usize,
+    loops: u32,
+    finished: bool,
+    current: Current,
+    hash: u64,
+    playing: bool,
+    speed: f32,
+    min_frame_delay: Duration,
+    direction: Direction,
+    seeking: bool,
+    hovered: bool,
+    visible: bool,
+    pressed: bool,
+    generation: u64,
+    appeared: Instant,
+    autoplay_at: Option<Instant>,
+    /// How long the current frame had been shown when playback was
+    /// last looked at, see [`Gif::elapsed`]
+    elapsed: Duration,
+    /// When frames last advanced, which [`Gif::max_fps`] counts from
+    advanced: Option<Instant>,
+    /// A step requested by [`step_forward`] or [`step_backward`]
+    step: Option<Direction>,
+    /// Whether playback has reached the frame it started on, for
+    /// [`Gif::on_enter_frame`]
+    entered: bool,
+    thumbnail: Option<Thumbnail>,
+    /// The shared [`Frames::flipped`] copy that's drawn
+    flipped: Option<Frames>,
+    rounded: Option<rounded::Rounded>,
+    /// When the [`EndAction`] of [`Gif::play_once_then`] applies
+    ending: Option<Instant>,
+    hidden: bool,
+}
+
+impl State {
+    /// The current frame's delay, scaled by the playback speed
+    /// and clamped to the minimum frame delay
+    fn delay(&self) -> Duration {
+        self.scaled(self.current.frame.delay)
+    }
+
+    fn scaled(&self, delay: Duration) -> Duration {
+        delay.div_f32(self.speed).max(self.min_frame_delay)
+    }
+}
+
+/// Every frame downscaled once to fit a [`Gif::thumbnail`] size
+struct Thumbnail {
+    /// The size given to [`Gif::thumbnail`]
+    requested: Size<u32>,
+    /// The size the frames were scaled to fit, which grows past the
+    /// requested size when the [`Gif`] is drawn larger
+    size: Size<u32>,
+    handles: Vec<Handle>,
+}
+
+impl Thumbnail {
+    /// How far, as a fraction, the drawn size may drift from
+    /// the scaled size before the frames are scaled again
+    const TOLERANCE: f32 = 0.1;
+
+    fn new(frames: &Frames, requested: Size<u32>, size: Size<u32>) -> Self {
+        let handles = (0..frames.len())
+            .map(|index| {
+                let frame = frames.frame(index);
+
+                match frame.rgba() {
+                    Some(rgba) => {
+                        let scaled = image_rs::DynamicImage::ImageRgba8(rgba)
+                            .thumbnail(size.width, size.height)
+                            .into_rgba8();
+
+                        Handle::from_rgba(scaled.width(), scaled.height(), scaled.into_raw())
+                    }
+                    None => frame.handle,
+                }
+            })
+            .collect();
+
+        Self {
+            requested,
+            size,
+            handles,
+        }
+    }
+
+    /// Whether the frames should be scaled again to fit `size`
+    fn is_stale(&self, requested: Size<u32>, size: Size<u32>) -> bool {
+        let drifted = |scaled: u32, target: u32| {
+            scaled.abs_diff(target) as f32 > scaled as f32 * Self::TOLERANCE
+        };
+
+        self.requested != requested
+            || drifted(self.size.width, size.width)
+            || drifted(self.size.height, size.height)
+    }
+}
+
+struct Current {
+    frame: Frame,
+    started: Instant,
+}
+
+impl From<Frame> for Current {
+    fn from(frame: Frame) -> Self {
+        Self {
+            started: Instant::now(),
+            frame,
+        }
+    }
+}
+
+/// A frame that displays a GIF while keeping aspect ratio
+///
+/// iced has no accessibility tree yet, so there's no way for a
+/// [`Gif`] to expose a description to screen readers. Describe
+/// meaningful gifs with visible text next to them instead.
+///
+/// Cloning is cheap, as the callbacks and [`Frames`] are shared.
+#[derive(Clone)]
+pub struct Gif<'a, Message> {
+    id: Option<Id>,
+    frames: Cow<'a, Frames>,
+    width: Length,
+    height: Length,
+    content_fit: ContentFit,
+    filter_method: FilterMethod,
+    rotation: Rotation,
+    flip_horizontal: bool,
+    flip_vertical: bool,
+    border_radius: Option<border::Radius>,
+    opacity: f32,
+    scale: f32,
+    fade_in: Option<Duration>,
+    playing: bool,
+    autoplay_after: Option<Duration>,
+    end: Option<(Duration, EndAction)>,
+    speed: f32,
+    min_frame_delay: Duration,
+    direction: Direction,
+    skip_frames: bool,
+    max_fps: u32,
+    clock: Option<Duration>,
+    redraw_mode: RedrawMode,
+    loop_count: Option<LoopCount>,
+    loop_range: Option<(usize, usize)>,
+    frame: Option<usize>,
+    paused_on: Option<usize>,
+    play_on_hover: bool,
+    thumbnail: Option<Size<u32>>,
+    generation: u64,
+    on_loop: Option<Rc<dyn Fn() -> Message + 'a>>,
+    on_finished: Option<Rc<dyn Fn() -> Message + 'a>>,
+    on_frame_changed: Option<Rc<dyn Fn(usize, Duration) -> Message + 'a>>,
+    on_enter_frame: Vec<(usize, Rc<dyn Fn() -> Message + 'a>)>,
+    on_frame: Option<Rc<dyn Fn(Handle) -> Message + 'a>>,
+    on_progress: Option<Rc<dyn Fn(f32) -> Message + 'a>>,
+    on_click: Option<Rc<dyn Fn() -> Message + 'a>>,
+    completion_flag: Option<Arc<AtomicBool>>,
+}
+
+impl<'a, Message> fmt::Debug for Gif<'a, Message> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Gif")
+            .field("id", &self.id)
+            .field("frames", &self.frames)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("content_fit", &self.content_fit)
+            .field("filter_method", &self.filter_method)
+            .field("rotation", &self.rotation)
+            .field("flip_horizontal", &self.flip_horizontal)
+            .field("flip_vertical", &self.flip_vertical)
+            .field("border_radius", &self.border_radius)
+            .field("opacity", &self.opacity)
+            .field("scale", &self.scale)
+            .field("fade_in", &self.fade_in)
+            .field("playing", &self.playing)
+            .field("autoplay_after", &self.autoplay_after)
+            .field("end", &self.end)
+            .field("speed", &self.speed)
+            .field("min_frame_delay", &self.min_frame_delay)
+            .field("direction", &self.direction)
+            .field("skip_frames", &self.skip_frames)
+            .field("max_fps", &self.max_fps)
+            .field("clock", &self.clock)
+            .field("redraw_mode", &self.redraw_mode)
+            .field("loop_count", &self.loop_count)
+            .field("loop_range", &self.loop_range)
+            .field("frame", &self.frame)
+            .field("paused_on", &self.paused_on)
+            .field("play_on_hover", &self.play_on_hover)
+            .field("thumbnail", &self.thumbnail)
+            .field("generation", &self.generation)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, Message> Gif<'a, Message> {
+    /// Creates a new [`Gif`] with the given [`Frames`]
+    pub fn new(frames: &'a Frames) -> Self {
+        Self::from_cow(Cow::Borrowed(frames))
+    }
+
+    /// Creates a new [`Gif`] that owns the given [`Frames`]
+    ///
+    /// This lets a [`Gif`] be built and kept around apart from
+    /// the state it was loaded into, then cloned into each view.
+    pub fn owned(frames: Frames) -> Self {
+        Self::from_cow(Cow::Owned(frames))
+    }
+
+    fn from_cow(frames: Cow<'a, Frames>) -> Self {
+        Gif {
+            id: None,
+            frames,
+            width: Length::Shrink,
+            height: Length::Shrink,
+            content_fit: ContentFit::default(),
+            filter_method: FilterMethod::default(),
+            rotation: Rotation::default(),
+            flip_horizontal: false,
+            flip_vertical: false,
+            border_radius: None,
+            opacity: 1.0,
+            scale: 1.0,
+            fade_in: None,
+            playing: true,
+            autoplay_after: None,
+            end: None,
+            speed: 1.0,
+            min_frame_delay: Duration::from_millis(20),
+            direction: Direction::default(),
+            skip_frames: true,
+            max_fps: 0,
+            clock: None,
+            redraw_mode: RedrawMode::Timed,
+            loop_count: None,
+            loop_range: None,
+            frame: None,
+            paused_on: None,
+            play_on_hover: false,
+            thumbnail: None,
+            generation: 0,
+            on_loop: None,
+            on_finished: None,
+            on_frame_changed: None,
+            on_enter_frame: vec![],
+            on_frame: None,
+            on_progress: None,
+            on_click: None,
+            completion_flag: None,
+        }
+    }
+
+    /// Sets the [`Id`] of the [`Gif`], for use with [`query_playback`],
+    /// [`step_forward`] and [`step_backward`].
+    pub fn id(mut self, id: Id) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Sets the width of the [`Gif`] boundaries.
+    pub fn width(mut self, width: Length) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Sets the height of the [`Gif`] boundaries.
+    pub fn height(mut self, height: Length) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Sets the width and height of the [`Gif`] boundaries to the
+    /// [`Frames::dimensions`], so it's drawn at its native size.
+    pub fn native_size(self) -> Self {
+        let (width, height) = self.frames.dimensions();
+
+        self.width(Length::Fixed(width as f32))
+            .height(Length::Fixed(height as f32))
+    }
+
+    /// Sets the [`ContentFit`] of the [`Image`].
+    ///
+    /// Defaults to [`ContentFit::Contain`]
+    pub fn content_fit(mut self, content_fit: ContentFit) -> Self {
+        self.content_fit = content_fit;
+        self
+    }
+
+    /// Sets the [`FilterMethod`] of the [`Image`].
+    pub fn filter_method(mut self, filter_method: FilterMethod) -> Self {
+        self.filter_method = filter_method;
+        self
+    }
+
+    /// Draws the [`Gif`] with nearest-neighbor filtering, keeping
+    /// pixel art crisp when scaled.
+    ///
+    /// This just sets [`FilterMethod::Nearest`].
+    pub fn pixelated(self) -> Self {
+        self.filter_method(FilterMethod::Nearest)
+    }
+
+    /// Applies the given [`Rotation`] to the [`Image`].
+    pub fn rotation(mut self, rotation: impl Into<Rotation>) -> Self {
+        self.rotation = rotation.into();
+        self
+    }
+
+    /// Sets whether the [`Gif`] is mirrored left to right.
+    ///
+    /// Frames are mirrored before any [`Rotation`], once for every
+    /// widget drawing them, see [`Frames::flipped`].
+    pub fn flip_horizontal(mut self, flip: bool) -> Self {
+        self.flip_horizontal = flip;
+        self
+    }
+
+    /// Sets whether the [`Gif`] is mirrored top to bottom.
+    ///
+    /// Frames are mirrored before any [`Rotation`], once for every
+    /// widget drawing them, see [`Frames::flipped`].
+    pub fn flip_vertical(mut self, flip: bool) -> Self {
+        self.flip_vertical = flip;
+        self
+    }
+
+    /// Rounds the corners of the [`Gif`] by the given [`border::Radius`].
+    ///
+    /// The corners of each frame are made transparent the first time
+    /// it's drawn, and cut again only once the drawn size moves the
+    /// radius by more than a tenth. The radius
+    /// applies to the frames before any [`Gif::rotation`], and is
+    /// stretched along with them by [`ContentFit::Fill`].
+    pub fn border_radius(mut self, radius: impl Into<border::Radius>) -> Self {
+        self.border_radius = Some(radius.into());
+        self
+    }
+
+    /// Sets the opacity of the [`Image`].
+    ///
+    /// It should be in the [0.0, 1.0] range—`0.0` meaning completely transparent,
+    /// and `1.0` meaning completely opaque.
+    pub fn opacity(mut self, opacity: impl Into<f32>) -> Self {
+        self.opacity = opacity.into();
+        self
+    }
+
+    /// Sets the multiplier applied to the native size of the [`Gif`]
+    /// before it's fit to its bounds.
+    ///
+    /// Combined with [`ContentFit::None`], this displays pixel art at
+    /// an exact integer scale. Values at or below zero are clamped to
+    /// a tiny positive value. Defaults to `1.0`.
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale.max(f32::EPSILON);
+        self
+    }
+
+    /// Fades the [`Gif`] in from transparent to its opacity over the
+    /// given duration when its [`Frames`] first appear.
+    pub fn fade_in(mut self, duration: impl Into<Option<Duration>>) -> Self {
+        self.fade_in = duration.into();
+        self
+    }
+
+    /// Sets whether the [`Gif`] is playing.
+    ///
+    /// When `false`, the current frame stays on screen until playback
+    /// is resumed. Defaults to `true`.
+    pub fn playing(mut self, playing: bool) -> Self {
+        self.playing = playing;
+        self
+    }
+
+    /// Holds the first frame of the [`Gif`] for the given duration
+    /// after it appears before animating.
+    ///
+    /// This allows staggering the start of several gifs.
+    pub fn autoplay_after(mut self, delay: impl Into<Option<Duration>>) -> Self {
+        self.autoplay_after = delay.into();
+        self
+    }
+
+    /// Plays the [`Gif`] once, holds its last frame for `hold`, and then
+    /// applies the given [`EndAction`].
+    ///
+    /// This overrides any [`Gif::loop_count`]. [`EndAction::Hide`]
+    /// collapses the [`Gif`] for transient cues, until it's restarted
+    /// with [`Gif::restart_signal`].
+    pub fn play_once_then(mut self, hold: Duration, then: EndAction) -> Self {
+        self.end = Some((hold, then));
+        self
+    }
+
+    /// Sets the playback speed multiplier of the [`Gif`].
+    ///
+    /// `2.0` plays twice as fast, `0.5` at half speed. Values at or
+    /// below zero are clamped to a tiny positive value. Defaults to `1.0`.
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.speed = speed.max(f32::EPSILON);
+        self
+    }
+
+    /// Sets the minimum delay between frames of the [`Gif`].
+    ///
+    /// Frames with shorter delays are held for at least this long,
+    /// leaving the decoded delays untouched. Defaults to 20ms.
+    pub fn min_frame_delay(mut self, delay: Duration) -> Self {
+        self.min_frame_delay = delay;
+        self
+    }
+
+    /// Sets the [`Direction`] the [`Gif`] plays in.
+    ///
+    /// Defaults to [`Direction::Forward`].
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Sets whether the [`Gif`] skips frames to keep up with real time
+    /// when redraws arrive late.
+    ///
+    /// When `false`, every frame is shown in turn. Defaults to `true`.
+    pub fn skip_frames(mut self, skip_frames: bool) -> Self {
+        self.skip_frames = skip_frames;
+        self
+    }
+
+    /// Drives playback from the given time since the [`Gif`] appeared,
+    /// instead of the real clock.
+    ///
+    /// The [`Gif`] no longer schedules redraws of its own, so pass a
+    /// later time and redraw to advance it. This renders the same
+    /// frames for the same times, e.g. when exporting video frame by
+    /// frame or in tests.
+    pub fn clock(mut self, elapsed: impl Into<Option<Duration>>) -> Self {
+        self.clock = elapsed.into();
+        self
+    }
+
+    /// Sets the [`RedrawMode`] of the [`Gif`].
+    ///
+    /// Defaults to [`RedrawMode::Timed`].
+    pub fn redraw_mode(mut self, redraw_mode: RedrawMode) -> Self {
+        self.redraw_mode = redraw_mode;
+        self
+    }
+
+    /// Caps how many times per second the [`Gif`] advances its frames.
+    ///
+    /// Frames due within the same interval are skipped over together,
+    /// which saves power on gifs with very short delays. With
+    /// [`Gif::skip_frames`] disabled, playback slows down instead.
+    /// `0` means uncapped, which is the default.
+    pub fn max_fps(mut self, max_fps: u32) -> Self {
+        self.max_fps = max_fps;
+        self
+    }
+
+    /// Sets the [`LoopCount`] of the [`Gif`], overriding the one
+    /// declared by its [`Frames`].
+    pub fn loop_count(mut self, loop_count: impl Into<Option<LoopCount>>) -> Self {
+        self.loop_count = loop_count.into();
+        self
+    }
+
+    /// Repeats only the frames from `start` to `end`, inclusive, once
+    /// playback reaches them.
+    ///
+    /// The frames before `start` play once as an intro, while those
+    /// after `end` are never shown. Both ends are clamped to the
+    /// frames there are. Played backwards, the frames after `end`
+    /// are the intro instead.
+    pub fn loop_range(mut self, start: usize, end: usize) -> Self {
+        self.loop_range = Some((start, end));
+        self
+    }
+
+    /// Displays the frame at the given index instead of animating.
+    ///
+    /// The index wraps around the number of frames. When unset,
+    /// playback resumes from the displayed frame.
+    pub fn frame(mut self, index: impl Into<Option<usize>>) -> Self {
+        self.frame = index.into();
+        self
+    }
+
+    /// Displays the frame shown at the given time into the animation
+    /// instead of animating.
+    ///
+    /// Times past the [`Frames::total_duration`] wrap around. See
+    /// [`Gif::frame`].
+    pub fn seek_to(self, time: Duration) -> Self {
+        let index = self.frames.index_at(time);
+
+        self.frame(index)
+    }
+
+    /// Starts the [`Gif`] paused on the frame at the given index.
+    ///
+    /// The index wraps around the number of frames. Chain
+    /// [`Gif::playing`] afterwards to start animating from that frame,
+    /// e.g. while hovered.
+    pub fn paused_on(mut self, index: usize) -> Self {
+        self.paused_on = Some(index);
+        self.playing = false;
+        self
+    }
+
+    /// Sets whether the [`Gif`] only animates while hovered.
+    ///
+    /// When the cursor leaves, the current frame stays on screen.
+    pub fn play_on_hover(mut self, play_on_hover: bool) -> Self {
+        self.play_on_hover = play_on_hover;
+        self
+    }
+
+    /// Downscales every frame once to fit within the given size,
+    /// keeping their aspect ratio.
+    ///
+    /// This saves uploading full resolution frames for a [`Gif`] that
+    /// is displayed much smaller than its native size. Frames are only
+    /// scaled again when the size changes, or when the [`Gif`] ends up
+    /// drawn noticeably larger or smaller than they were scaled to, so
+    /// they stay crisp as the window is resized.
+    pub fn thumbnail(mut self, size: impl Into<Option<Size<u32>>>) -> Self {
+        self.thumbnail = size.into();
+        self
+    }
+
+    /// Restarts the animation from its first frame whenever the given
+    /// generation changes.
+    ///
+    /// Bump the generation, e.g. from a replay button, to play the
+    /// [`Gif`] again without loading new [`Frames`].
+    pub fn restart_signal(mut self, generation: u64) -> Self {
+        self.generation = generation;
+        self
+    }
+
+    /// Sets the message that should be produced each time the
+    /// [`Gif`] wraps around to its first frame.
+    pub fn on_loop(mut self, message: Message) -> Self
+    where
+        Message: Clone + 'a,
+    {
+        self.on_loop = Some(Rc::new(move || message.clone()));
+        self
+    }
+
+    /// Sets the message that should be produced once a [`Gif`] with a
+    /// finite [`LoopCount`] finishes its last loop.
+    ///
+    /// This is never produced for gifs that loop forever.
+    pub fn on_finished(mut self, message: Message) -> Self
+    where
+        Message: Clone + 'a,
+    {
+        self.on_finished = Some(Rc::new(move || message.clone()));
+        self
+    }
+
+    /// Sets a flag that is raised once a [`Gif`] with a finite
+    /// [`LoopCount`] finishes its last loop.
+    ///
+    /// This lets playback completion be polled from outside the widget.
+    /// It's never raised for gifs that loop forever.
+    pub fn completion_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.completion_flag = Some(flag);
+        self
+    }
+
+    /// Sets the message that should be produced when the [`Gif`]
+    /// advances to a new frame.
+    ///
+    /// The closure receives the index and delay of the new frame.
+    pub fn on_frame_changed(
+        mut self,
+        on_frame_changed: impl Fn(usize, Duration) -> Message + 'a,
+    ) -> Self {
+        self.on_frame_changed = Some(Rc::new(on_frame_changed));
+        self
+    }
+
+    /// Adds a message that should be produced each time playback of
+    /// the [`Gif`] reaches the frame at the given index.
+    ///
+    /// That includes the frame playback starts on, and every loop back
+    /// around to it, even when a [`Gif::loop_range`] holds only that
+    /// frame. Any number of frames can have messages. Frames passed
+    /// over by [`Gif::skip_frames`] produce nothing.
+    pub fn on_enter_frame(mut self, index: usize, message: Message) -> Self
+    where
+        Message: Clone + 'a,
+    {
+        self.on_enter_frame
+            .push((index, Rc::new(move || message.clone())));
+        self
+    }
+
+    /// Sets the message that should be produced with the image
+    /// [`Handle`] of each new frame the [`Gif`] advances to.
+    ///
+    /// This lets the displayed frame be drawn elsewhere, e.g. as
+    /// a reflection.
+    pub fn on_frame(mut self, on_frame: impl Fn(Handle) -> Message + 'a) -> Self {
+        self.on_frame = Some(Rc::new(on_frame));
+        self
+    }
+
+    /// Sets the message that should be produced with how far through
+    /// the current loop the [`Gif`] is, from `0.0` to `1.0`.
+    ///
+    /// This is produced once per frame advance rather than every
+    /// redraw, to avoid flooding the update loop.
+    pub fn on_progress(mut self, on_progress: impl Fn(f32) -> Message + 'a) -> Self {
+        self.on_progress = Some(Rc::new(on_progress));
+        self
+    }
+
+    /// Sets the message that should be produced when the [`Gif`]
+    /// is clicked.
+    ///
+    /// A click is a press and release that both happen within
+    /// the bounds of the [`Gif`].
+    pub fn on_click(mut self, message: Message) -> Self
+    where
+        Message: Clone + 'a,
+    {
+        self.on_click = Some(Rc::new(move || message.clone()));
+        self
+    }
+
+    /// Draws `handle` as an image of `image_size` within `bounds`
+    ///
+    /// Mirrors `iced_widget::image::draw`, which measures the drawn
+    /// handle itself.
+    fn draw_frame<Renderer>(
+        &self,
+        renderer: &mut Renderer,
+        bounds: Rectangle,
+        image_size: Size,
+        handle: &Handle,
+        opacity: f32,
+    ) where
+        Renderer: image::Renderer<Handle = Handle>,
+    {
+        let rotated_size = self.rotation.apply(image_size);
+
+        let adjusted_fit = self.content_fit.fit(rotated_size, bounds.size());
+
+        let scale = Vector::new(
+            adjusted_fit.width / rotated_size.width,
+            adjusted_fit.height / rotated_size.height,
+        );
+
+        let final_size = image_size * scale;
+
+        // `ScaleDown` never fits larger than the image itself, and is
+        // centered within the bounds like every fit but `None`. `Fill`
+        // scales each axis to the bounds, so it's drawn edge to edge.
+        let position = match self.content_fit {
+            ContentFit::None => Point::new(
+                bounds.x + (rotated_size.width - adjusted_fit.width) / 2.0,
+                bounds.y + (rotated_size.height - adjusted_fit.height) / 2.0,
+            ),
+            _ => Point::new(
+                bounds.center_x() - final_size.width / 2.0,
+                bounds.center_y() - final_size.height / 2.0,
+            ),
+        };
+
+        let drawing_bounds = Rectangle::new(position, final_size);
+
+        let render = |renderer: &mut Renderer| {
+            renderer.draw_image(
+                image::Image {
+                    handle: handle.clone(),
+                    filter_method: self.filter_method,
+                    rotation: self.rotation.radians(),
+                    opacity,
+                    snap: true,
+                },
+                drawing_bounds,
+            );
+        };
+
+        if adjusted_fit.width > bounds.width || adjusted_fit.height > bounds.height {
+            renderer.with_layer(bounds, render);
+        } else {
+            render(renderer);
+        }
+    }
+
+    /// Steps `state` to the next frame index in the playback direction
+    ///
+    /// Returns `false` if playback finished instead.
+    fn step(&self, state: &mut State, shell: &mut Shell<'_, Message>) -> bool {
+        let len = self.frames.len();
+
+        let (start, end) = self.loop_range.map_or((0, len - 1), |(start, end)| {
+            let end = end.min(len - 1);
+
+            (start.min(end), end)
+        });
+
+        let (next, wraps) = match state.direction {
+            Direction::Forward => {
+                let wraps = state.index == end || state.index + 1 >= len;
+
+                (if wraps { start } else { state.index + 1 }, wraps)
+            }
+            Direction::Backward => {
+                let wraps = state.index == start || state.index == 0;
+
+                (if wraps { end } else { state.index - 1 }, wraps)
+            }
+        };
+
+        if wraps {
+            // Settle on the last frame once all
+            // loops have played
+            // Playing once wins over any loop count, whichever
+            // was set last
+            let loop_count = match self.end {
+                Some(_) => LoopCount::Finite(0),
+                None => self.loop_count.unwrap_or(self.frames.loop_count),
+            };
+
+            if let LoopCount::Finite(count) = loop_count {
+                if state.loops >= count {
+                    state.finished = true;
+
+                    if let Some(flag) = &self.completion_flag {
+                        flag.store(true, atomic::Ordering::Relaxed);
+                    }
+
+                    if let Some(on_finished) = &self.on_finished {
+                        shell.publish(on_finished());
+                    }
+
+                    return false;
+                }
+            }
+
+            state.loops = state.loops.saturating_add(1);
+
+            if let Some(on_loop) = &self.on_loop {
+                shell.publish(on_loop());
+            }
+        }
+
+        state.index = next;
+
+        true
+    }
+
+    /// Publishes the messages of [`Gif::on_enter_frame`] for playback
+    /// reaching the frame at `index`
+    fn entered_frame(&self, index: usize, shell: &mut Shell<'_, Message>) {
+        for (target, on_enter_frame) in &self.on_enter_frame {
+            if *target == index {
+                shell.publish(on_enter_frame());
+            }
+        }
+    }
+
+    /// Publishes the messages for a change to the current frame
+    fn frame_changed(&self, state: &State, shell: &mut Shell<'_, Message>) {
+        if let Some(on_frame_changed) = &self.on_frame_changed {
+            shell.publish(on_frame_changed(state.index, state.current.frame.delay));
+        }
+
+        if let Some(on_frame) = &self.on_frame {
+            shell.publish(on_frame(state.current.frame.handle.clone()));
+        }
+
+        if let Some(on_progress) = &self.on_progress {
+            shell.publish(on_progress(
+                self.frames.progress(state.index, state.direction),
+            ));
+        }
+    }
+
+    /// Delays `at` so frames advance at most [`Gif::max_fps`] times
+    /// per second since they last did
+    fn capped(&self, state: &State, at: Instant) -> Instant {
+        match (self.max_fps, state.advanced) {
+            (0, _) | (_, None) => at,
+            (max_fps, Some(advanced)) => at.max(advanced + Duration::from_secs(1) / max_fps),
+        }
+    }
+
+    /// The opacity to draw with, partway through any fade in
+    fn faded_opacity(&self, state: &State) -> f32 {
+        match self.fade_in {
+            Some(fade_in) if !fade_in.is_zero() => {
+                let progress = self
+                    .now(state)
+                    .saturating_duration_since(state.appeared)
+                    .as_secs_f32()
+                    / fade_in.as_secs_f32();
+
+                self.opacity * progress.min(1.0)
+            }
+            _ => self.opacity,
+        }
+    }
+
+    /// The current time, from [`Gif::clock`] if it's set
+    fn now(&self, state: &State) -> Instant {
+        self.clock
+            .map_or_else(Instant::now, |elapsed| state.appeared + elapsed)
+    }
+
+    /// Whether playback is moving through the frames, rather than
+    /// being paused or otherwise held on one
+    fn is_advancing(&self, state: &State) -> bool {
+        state.playing
+            && !state.finished
+            && !state.seeking
+            && !state.hidden
+            && state.visible
+            && state.autoplay_at.is_none()
+            && (!self.play_on_hover || state.hovered)
+    }
+
+    /// How long the current frame has been shown, by the same clock
+    /// playback uses
+    ///
+    /// While playback isn't advancing, this holds at what it was
+    /// when it stopped, so it has to be updated before any change
+    /// to the playback state.
+    fn elapsed(&self, state: &State) -> Duration {
+        if self.is_advancing(state) {
+            self.now(state)
+                .saturating_duration_since(state.current.started)
+        } else {
+            state.elapsed
+        }
+    }
+
+    /// Requests a redraw as the [`RedrawMode`] schedules it, unless
+    /// driven by [`Gif::clock`]
+    fn request_redraw(&self, shell: &mut Shell<'_, Message>, request: window::RedrawRequest) {
+        if self.clock.is_some() {
+            return;
+        }
+
+        match self.redraw_mode {
+            RedrawMode::Timed => shell.request_redraw(request),
+            RedrawMode::Continuous => shell.request_redraw(window::RedrawRequest::NextFrame),
+        }
+    }
+
+    fn new_state(&self) -> State {
+        let len = self.frames.len();
+
+        let index = match (self.frame.or(self.paused_on), self.direction) {
+            (Some(index), _) => index.checked_rem(len).unwrap_or(0),
+            (None, Direction::Forward) => 0,
+            (None, Direction::Backward) => len.saturating_sub(1),
+        };
+
+        let appeared = Instant::now();
+
+        State {
+            index,
+            start: index,
+            len,
+            loops: 0,
+            finished: false,
+            // The first frame starts as the [`Gif::clock`] does
+            current: Current {
+                frame: self.frames.frame(index),
+                started: appeared,
+            },
+            hash: self.frames.hash,
+            playing: self.playing,
+            speed: self.speed,
+            min_frame_delay: self.min_frame_delay,
+            direction: self.direction,
+            seeking: self.frame.is_some(),
+            hovered: false,
+            visible: true,
+            pressed: false,
+            generation: self.generation,
+            appeared,
+            autoplay_at: self.autoplay_after.map(|delay| appeared + delay),
+            elapsed: Duration::ZERO,
+            advanced: None,
+            step: None,
+            entered: false,
+            thumbnail: None,
+            flipped: None,
+            rounded: None,
+            ending: None,
+            hidden: false,
+        }
+    }
+}
+
+impl<'a, Message, Theme, Renderer> Widget<Message, Theme, Renderer> for Gif<'a, Message>
+where
+    Renderer: image::Renderer<Handle = Handle>,
+{
+    fn size(&self) -> Size<Length> {
+        Size::new(self.width, self.height)
+    }
+
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(self.new_state())
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        let state = tree.state.downcast_mut::<State>();
+
+        state.elapsed = self.elapsed(state);
+
+        // Reset state if new gif Frames is used w/
+        // same state tree.
+        if state.hash != self.frames.hash {
+            *state = self.new_state();
+        } else if state.generation != self.generation {
+            // Same frames, so the thumbnails can be kept
+            let thumbnail = state.thumbnail.take();
+            let flipped = state.flipped.take();
+            let rounded = state.rounded.take();
+
+            *state = State {
+                thumbnail,
+                flipped,
+                rounded,
+                appeared: state.appeared,
+                ..self.new_state()
+            };
+        }
+
+        state.len = self.frames.len();
+        state.speed = self.speed;
+        state.min_frame_delay = self.min_frame_delay;
+        state.direction = self.direction;
+
+        match self.frame {
+            Some(index) => {
+                let index = index.checked_rem(self.frames.len()).unwrap_or(0);
+
+                if !state.seeking || state.index != index {
+                    state.index = index;
+                    state.current = self.frames.frame(index).into();
+                    state.finished = false;
+                    state.elapsed = Duration::ZERO;
+                }
+
+                state.seeking = true;
+            }
+            // Continue playback from the seeked frame
+            None if state.seeking => {
+                state.seeking = false;
+                state.start = state.index;
+                state.current.started = self.now(state);
+            }
+            None => {}
+        }
+
+        // Restart the current frame's delay on resume so
+        // we don't instantly jump to the next frame
+        if state.playing != self.playing {
+            state.playing = self.playing;
+
+            if state.playing {
+                state.current.started = self.now(state);
+            }
+        }
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        let state = tree.state.downcast_mut::<State>();
+
+        // Mirrors `iced_widget::image::layout`, with the
+        // native size scaled first
+        let native_size = renderer.measure_image(&self.frames.first.handle);
+        let image_size =
+            Size::new(native_size.width as f32, native_size.height as f32) * self.scale;
+
+        let rotated_size = self.rotation.apply(image_size);
+
+        let raw_size = limits.resolve(self.width, self.height, rotated_size);
+
+        let full_size = self.content_fit.fit(rotated_size, raw_size);
+
+        let final_size = Size {
+            width: match self.width {
+                Length::Shrink => f32::min(raw_size.width, full_size.width),
+                _ => raw_size.width,
+            },
+            height: match self.height {
+                Length::Shrink => f32::min(raw_size.height, full_size.height),
+                _ => raw_size.height,
+            },
+        };
+
+        // The size the frames end up drawn at
+        let fit = self.content_fit.fit(rotated_size, final_size);
+        let drawn = image_size
+            * f32::max(
+                fit.width / rotated_size.width,
+                fit.height / rotated_size.height,
+            );
+
+        // Mirrored frames are shared with every other widget
+        // flipping the same frames
+        let flipped = match (self.flip_horizontal, self.flip_vertical) {
+            (false, false) => None,
+            (horizontal, vertical) => Some(self.frames.flipped(horizontal, vertical)),
+        };
+
+        let key = |frames: &Frames| (frames.hash, frames.len());
+        let mut rescaled = state.flipped.as_ref().map(key) != flipped.as_ref().map(key);
+
+        state.flipped = flipped;
+
+        match self.thumbnail {
+            Some(requested) => {
+                // Thumbnails drawn larger than requested are scaled
+                // to the drawn size instead, up to the native size,
+                // so they stay crisp as the window grows
+                let size = Size::new(
+                    requested
+                        .width
+                        .max(drawn.width.ceil() as u32)
+                        .min(native_size.width),
+                    requested
+                        .height
+                        .max(drawn.height.ceil() as u32)
+                        .min(native_size.height),
+                );
+
+                // Partial frames from a stream grow without
+                // changing their hash
+                if rescaled
+                    || state.thumbnail.as_ref().is_none_or(|thumbnail| {
+                        thumbnail.handles.len() != self.frames.len()
+                            || thumbnail.is_stale(requested, size)
+                    })
+                {
+                    let frames = state.flipped.as_ref().unwrap_or(&self.frames);
+
+                    state.thumbnail = Some(Thumbnail::new(frames, requested, size));
+                    rescaled = true;
+                }
+            }
+            None => rescaled |= state.thumbnail.take().is_some(),
+        }
+
+        // Corners are cut from whichever frames are drawn, at a
+        // radius in their own pixels
+        let source = state
+            .thumbnail
+            .as_ref()
+            .map(|thumbnail| thumbnail.handles.as_slice());
+
+        let radii = self.border_radius.map(|radius| {
+            let source_size = source
+                .and_then(|handles| handles.first())
+                .map_or(native_size, |handle| renderer.measure_image(handle));
+
+            // `ContentFit::Fill` stretches each axis apart, like
+            // `draw_frame` does
+            let scale = Vector::new(
+                source_size.width as f32 / (image_size.width * fit.width / rotated_size.width),
+                source_size.height as f32 / (image_size.height * fit.height / rotated_size.height),
+            );
+
+            let pixels = |radius: f32| match radius {
+                radius if radius.is_finite() => radius.round() as u32,
+                _ => 0,
+            };
+
+            [
+                radius.top_left,
+                radius.top_right,
+                radius.bottom_right,
+                radius.bottom_left,
+            ]
+            .map(|radius| (pixels(radius * scale.x), pixels(radius * scale.y)))
+        });
+
+        match radii {
+            Some(radii) if radii.iter().any(|&(x, y)| x > 0 && y > 0) => {
+                if rescaled
+                    || state.rounded.as_ref().is_none_or(|rounded| {
+                        rounded.len() != self.frames.len() || rounded.is_stale(radii)
+                    })
+                {
+                    state.rounded = Some(rounded::Rounded::new(self.frames.len(), radii));
+                }
+            }
+            _ => state.rounded = None,
+        }
+
+        if state.hidden {
+            return layout::Node::new(Size::ZERO);
+        }
+
+        layout::Node::new(final_size)
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        _layout: Layout<'_>,
+        _renderer: &Renderer,
+        operation: &mut dyn Operation,
+    ) {
+        let state = tree.state.downcast_mut::<State>();
+
+        state.elapsed = self.elapsed(state);
+
+        operation.custom(state, self.id.as_ref());
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: Cursor,
+        _renderer: &Renderer,
+        _clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        let state = tree.state.downcast_mut::<State>();
+
+        state.elapsed = self.elapsed(state);
+
+        // Play as if redrawn at the time of the clock instead
+        let event = match (event, self.clock) {
+            (Event::Window(window::Event::RedrawRequested(_)), Some(_)) => {
+                Event::Window(window::Event::RedrawRequested(self.now(state)))
+            }
+            (event, _) => event,
+        };
+
+        if let Some(on_click) = &self.on_click {
+            let is_over = cursor.is_over(layout.bounds());
+
+            match event {
+                Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                | Event::Touch(touch::Event::FingerPressed { .. })
+                    if is_over =>
+                {
+                    state.pressed = true;
+
+                    return event::Status::Captured;
+                }
+                // Only a press that started on the gif counts
+                Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                | Event::Touch(touch::Event::FingerLifted { .. })
+                    if state.pressed && is_over =>
+                {
+                    state.pressed = false;
+
+                    shell.publish(on_click());
+
+                    return event::Status::Captured;
+                }
+                Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                | Event::Touch(
+                    touch::Event::FingerLifted { .. } | touch::Event::FingerLost { .. },
+                ) => {
+                    state.pressed = false;
+                }
+                _ => {}
+            }
+        }
+
+        // Nothing to animate
+        if self.frames.is_empty() {
+            return event::Status::Ignored;
+        }
+
+        // Steps recorded by `step_forward` and `step_backward` only
+        // apply while paused, and not while a frame is being
+        // displayed instead
+        if let Some(direction) = state.step.take() {
+            let len = self.frames.len();
+
+            if !state.playing && !state.seeking {
+                state.index = match direction {
+                    Direction::Forward => (state.index + 1) % len,
+                    Direction::Backward => (state.index + len - 1) % len,
+                };
+                state.current = self.frames.frame(state.index).into();
+                state.finished = false;
+                state.elapsed = Duration::ZERO;
+
+                self.frame_changed(state, shell);
+                self.entered_frame(state.index, shell);
+
+                shell.request_redraw(window::RedrawRequest::NextFrame);
+            }
+        }
+
+        // Stop scheduling redraws while scrolled out of view
+        let visible = layout.bounds().intersects(viewport);
+
+        if visible != state.visible {
+            state.visible = visible;
+
+            // Pick back up from the current frame
+            if visible {
+                state.current.started = self.now(state);
+            }
+        }
+
+        if !visible {
+            return event::Status::Ignored;
+        }
+
+        // Keep redrawing until the fade in completes, even
+        // while playback is paused
+        if let Event::Window(window::Event::RedrawRequested(now)) = event {
+            if self
+                .fade_in
+                .is_some_and(|fade_in| now.duration_since(state.appeared) < fade_in)
+            {
+                self.request_redraw(shell, window::RedrawRequest::NextFrame);
+            }
+        }
+
+        if self.play_on_hover {
+            let hovered = cursor.is_over(layout.bounds());
+
+            if hovered != state.hovered {
+                state.hovered = hovered;
+
+                // Pick back up from the current frame
+                if hovered {
+                    state.current.started = self.now(state);
+
+                    self.request_redraw(shell, window::RedrawRequest::NextFrame);
+                }
+            }
+
+            if !hovered {
+                return event::Status::Ignored;
+            }
+        }
+
+        // Apply the end action once the last frame has been held
+        if let (Some((_, action)), Some(ending)) = (self.end, state.ending) {
+            if let Event::Window(window::Event::RedrawRequested(now)) = event {
+                if now < ending {
+                    self.request_redraw(shell, window::RedrawRequest::At(ending));
+                } else {
+                    state.ending = None;
+
+                    match action {
+                        EndAction::HoldFirst => {
+                            state.index = state.start.min(self.frames.len() - 1);
+                            state.current = self.frames.frame(state.index).into();
+                        }
+                        EndAction::HoldLast => {}
+                        EndAction::Hide => {
+                            state.hidden = true;
+                            shell.invalidate_layout();
+                        }
+                    }
+
+                    self.request_redraw(shell, window::RedrawRequest::NextFrame);
+                }
+            }
+        }
+
+        // A single frame never changes, so there's nothing to schedule
+        if !state.playing || state.finished || state.seeking || self.frames.len() == 1 {
+            return event::Status::Ignored;
+        }
+
+        if let Event::Window(window::Event::RedrawRequested(now)) = event {
+            // Hold the first frame until autoplay starts
+            if let Some(autoplay_at) = state.autoplay_at {
+                if now < autoplay_at {
+                    self.request_redraw(shell, window::RedrawRequest::At(autoplay_at));
+
+                    return event::Status::Ignored;
+                }
+
+                state.autoplay_at = None;
+                state.current.started = now;
+            }
+
+            // Playback reaches the frame it starts on as it starts
+            if !state.entered {
+                state.entered = true;
+
+                self.entered_frame(state.index, shell);
+            }
+
+            // Whatever asked for this redraw, frames don't advance
+            // again before the interval of `max_fps` has passed
+            let earliest = self.capped(state, now);
+
+            if now < earliest {
+                self.request_redraw(shell, window::RedrawRequest::At(earliest));
+
+                return event::Status::Ignored;
+            }
+
+            let mut elapsed = now.duration_since(state.current.started);
+            let mut delay = state.delay();
+
+            if elapsed <= delay {
+                self.request_redraw(
+                    shell,
+                    window::RedrawRequest::At(self.capped(state, now + (delay - elapsed))),
+                );
+
+                return event::Status::Ignored;
+            }
+
+            let previous = state.index;
+
+            // Skip however many frames' worth of time has passed, up
+            // to a single loop, so playback keeps up with the clock
+            let mut caught_up = false;
+            let mut stepped = false;
+
+            for _ in 0..self.frames.len() {
+                elapsed -= delay;
+
+                if !self.step(state, shell) {
+                    break;
+                }
+
+                stepped = true;
+
+                delay = state.scaled(self.frames.delay(state.index));
+
+                if !self.skip_frames || elapsed <= delay {
+                    caught_up = true;
+                    break;
+                }
+            }
+
+            state.advanced = Some(now);
+
+            if state.index != previous {
+                state.current = self.frames.frame(state.index).into();
+
+                self.frame_changed(state, shell);
+            }
+
+            // Looping through a single frame range reaches the same
+            // frame again
+            if stepped {
+                self.entered_frame(state.index, shell);
+            }
+
+            state.current.started = if self.skip_frames && caught_up {
+                now.checked_sub(elapsed).unwrap_or(now)
+            } else {
+                now
+            };
+
+            if !state.finished {
+                self.request_redraw(
+                    shell,
+                    window::RedrawRequest::At(
+                        self.capped(state, state.current.started + state.delay()),
+                    ),
+                );
+            } else if let Some((hold, _)) = self.end {
+                let ending = now + hold;
+
+                state.ending = Some(ending);
+                self.request_redraw(shell, window::RedrawRequest::At(ending));
+            }
+        }
+
+        event::Status::Ignored
+    }
+
+    fn mouse_interaction(
+        &self,
+        _tree: &Tree,
+        layout: Layout<'_>,
+        cursor: Cursor,
+        _viewport: &Rectangle,
+        _renderer: &Renderer,
+    ) -> mouse::Interaction {
+        if self.on_click.is_some() && cursor.is_over(layout.bounds()) {
+            mouse::Interaction::Pointer
+        } else {
+            mouse::Interaction::Idle
+        }
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        _theme: &Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        _cursor: Cursor,
+        _viewport: &Rectangle,
+    ) {
+        if self.frames.is_empty() {
+            return;
+        }
+
+        let state = tree.state.downcast_ref::<State>();
+
+        if state.hidden {
+            return;
+        }
+
+        let mut handle = match (&state.thumbnail, &state.flipped) {
+            (Some(thumbnail), _) => thumbnail.handles.get(state.index).cloned(),
+            (None, Some(flipped)) => Some(flipped.frame(state.index).handle),
+            (None, None) => None,
+        }
+        .unwrap_or_else(|| state.current.frame.handle.clone());
+
+        if let Some(rounded) = &state.rounded {
+            handle = rounded.handle(state.index, &handle);
+        }
+
+        // Thumbnails are drawn at the size of the full frame
+        let Size { width, height } = renderer.measure_image(&state.current.frame.handle);
+
+        self.draw_frame(
+            renderer,
+            layout.bounds(),
+            Size::new(width as f32, height as f32) * self.scale,
+            &handle,
+            self.faded_opacity(state),
+        );
     }
 }
 
-impl<'a, Message, Theme, Renderer> From<Gif<'a>> for Element<'a, Message, Theme, Renderer>
+impl<'a, Message, Theme, Renderer> From<Gif<'a, Message>> for Element<'a, Message, Theme, Renderer>
 where
+    Message: 'a,
     Renderer: image::Renderer<Handle = Handle> + 'a,
 {
-    fn from(gif: Gif<'a>) -> Element<'a, Message, Theme, Renderer> {
+    fn from(gif: Gif<'a, Message>) -> Element<'a, Message, Theme, Renderer> {
         Element::new(gif)
     }
 }
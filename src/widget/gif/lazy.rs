@@ -0,0 +1,260 @@
+//! Decode gif frames on demand
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use iced_widget::core::image;
+
+use super::{bleed_edges, Error, Frame, LoopCount};
+
+/// The number of decoded frames kept around
+const WINDOW: usize = 4;
+
+/// The frames of a gif that are decoded as playback reaches them
+pub(super) struct Lazy {
+    bytes: Arc<[u8]>,
+    delays: Vec<Duration>,
+    cache: Mutex<Cache>,
+}
+
+/// Clones share the bytes and cached frames, but decode
+/// anything else on their own
+impl Clone for Lazy {
+    fn clone(&self) -> Self {
+        let window = self
+            .cache
+            .lock()
+            .map(|cache| cache.window.clone())
+            .unwrap_or_default();
+
+        Lazy {
+            bytes: self.bytes.clone(),
+            delays: self.delays.clone(),
+            cache: Mutex::new(Cache {
+                window,
+                decoder: None,
+            }),
+        }
+    }
+}
+
+impl Lazy {
+    /// Scans the frame delays of the gif and decodes its first frame
+    pub fn new(bytes: Vec<u8>) -> Result<(Self, Frame, LoopCount), Error> {
+        let bytes: Arc<[u8]> = bytes.into();
+
+        let (delays, loop_count) = scan(&bytes)?;
+
+        let mut decoder = Decoder::new(io::Cursor::new(bytes.clone()))?;
+
+        let first = decoder.next_frame()?.ok_or(Error::NoFrames)?;
+
+        let lazy = Lazy {
+            bytes,
+            delays,
+            cache: Mutex::new(Cache {
+                window: VecDeque::from([(0, first.clone())]),
+                decoder: Some(decoder),
+            }),
+        };
+
+        Ok((lazy, first, loop_count))
+    }
+
+    pub fn len(&self) -> usize {
+        self.delays.len()
+    }
+
+    pub fn delay(&self, index: usize) -> Duration {
+        self.delays[index]
+    }
+
+    /// Returns a copy with every delay divided by `factor`
+    pub fn with_speed(&self, factor: f32) -> Self {
+        let mut lazy = self.clone();
+
+        for delay in &mut lazy.delays {
+            *delay = delay.div_f32(factor);
+        }
+
+        lazy
+    }
+
+    /// Returns the frame at `index`, decoding it if it isn't cached
+    ///
+    /// Frames are composited sequentially, so jumping backwards
+    /// out of the cached window decodes again from the start.
+    pub fn frame(&self, index: usize) -> Option<Frame> {
+        let mut cache = self.cache.lock().ok()?;
+
+        let delay = *self.delays.get(index)?;
+
+        if let Some((_, frame)) = cache.window.iter().find(|(i, _)| *i == index) {
+            return Some(Frame {
+                delay,
+                handle: frame.handle.clone(),
+            });
+        }
+
+        if cache
+            .decoder
+            .as_ref()
+            .is_none_or(|decoder| decoder.next > index)
+        {
+            cache.decoder = Some(Decoder::new(io::Cursor::new(self.bytes.clone())).ok()?);
+        }
+
+        loop {
+            let decoder = cache.decoder.as_mut()?;
+            let next = decoder.next;
+
+            let Ok(Some(frame)) = decoder.next_frame() else {
+                cache.decoder = None;
+                return None;
+            };
+
+            if cache.window.len() == WINDOW {
+                cache.window.pop_front();
+            }
+            cache.window.push_back((next, frame.clone()));
+
+            if next == index {
+                return Some(Frame { delay, ..frame });
+            }
+        }
+    }
+}
+
+struct Cache {
+    window: VecDeque<(usize, Frame)>,
+    decoder: Option<Decoder<io::Cursor<Arc<[u8]>>>>,
+}
+
+/// Decodes gif frames onto a running canvas
+///
+/// Frames are decoded as soon as `R` has produced their bytes,
+/// so this also decodes gifs that are still arriving.
+pub(super) struct Decoder<R: io::Read> {
+    reader: gif_rs::Decoder<R>,
+    canvas: Vec<u8>,
+    width: u32,
+    height: u32,
+    next: usize,
+}
+
+impl<R: io::Read> Decoder<R> {
+    pub fn new(reader: R) -> Result<Self, Error> {
+        let mut options = gif_rs::DecodeOptions::new();
+        options.set_color_output(gif_rs::ColorOutput::RGBA);
+
+        let reader = options.read_info(reader).map_err(info_error)?;
+
+        let width = u32::from(reader.width());
+        let height = u32::from(reader.height());
+
+        Ok(Decoder {
+            reader,
+            canvas: vec![0; width as usize * height as usize * 4],
+            width,
+            height,
+            next: 0,
+        })
+    }
+
+    pub fn next_frame(&mut self) -> Result<Option<Frame>, Error> {
+        // Any interlaced rows come back in order
+        let Some(frame) = self.reader.read_next_frame().map_err(decoding_error)? else {
+            return Ok(None);
+        };
+
+        let previous =
+            (frame.dispose == gif_rs::DisposalMethod::Previous).then(|| self.canvas.clone());
+
+        let left = u32::from(frame.left);
+        let top = u32::from(frame.top);
+        let frame_width = u32::from(frame.width);
+        let right = (left + frame_width).min(self.width);
+        let bottom = (top + u32::from(frame.height)).min(self.height);
+
+        for y in top..bottom {
+            for x in left..right {
+                let src = (((y - top) * frame_width + (x - left)) * 4) as usize;
+                let dst = ((y * self.width + x) * 4) as usize;
+
+                // Transparent pixels let the canvas show through
+                if let Some(pixel) = frame.buffer.get(src..src + 4) {
+                    if pixel[3] != 0 {
+                        self.canvas[dst..dst + 4].copy_from_slice(pixel);
+                    }
+                }
+            }
+        }
+
+        let delay = Duration::from_millis(u64::from(frame.delay) * 10);
+        let mut pixels = self.canvas.clone();
+        bleed_edges(&mut pixels, self.width);
+
+        let handle = image::Handle::from_rgba(self.width, self.height, pixels);
+
+        match frame.dispose {
+            gif_rs::DisposalMethod::Background => {
+                for y in top..bottom {
+                    let start = ((y * self.width + left) * 4) as usize;
+                    let end = ((y * self.width + right) * 4) as usize;
+
+                    self.canvas[start..end].fill(0);
+                }
+            }
+            gif_rs::DisposalMethod::Previous => {
+                if let Some(previous) = previous {
+                    self.canvas = previous;
+                }
+            }
+            gif_rs::DisposalMethod::Any | gif_rs::DisposalMethod::Keep => {}
+        }
+
+        self.next += 1;
+
+        Ok(Some(Frame { delay, handle }))
+    }
+
+    /// The loop count of the gif, as far as it's been read
+    pub fn loop_count(&self) -> LoopCount {
+        self.reader.repeat().into()
+    }
+}
+
+/// Reads the delay of every frame without decoding any pixels
+fn scan(bytes: &[u8]) -> Result<(Vec<Duration>, LoopCount), Error> {
+    let mut options = gif_rs::DecodeOptions::new();
+    options.skip_frame_decoding(true);
+
+    let mut reader = options.read_info(bytes).map_err(info_error)?;
+    let mut delays = vec![];
+
+    while let Some(frame) = reader.read_next_frame().map_err(decoding_error)? {
+        delays.push(Duration::from_millis(u64::from(frame.delay) * 10));
+    }
+
+    Ok((delays, reader.repeat().into()))
+}
+
+/// Maps an error reading the gif header, where a gif that ends
+/// before its first frame has no frames at all
+fn info_error(error: gif_rs::DecodingError) -> Error {
+    match error {
+        gif_rs::DecodingError::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof => {
+            Error::NoFrames
+        }
+        error => decoding_error(error),
+    }
+}
+
+fn decoding_error(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Error {
+    image_rs::ImageError::Decoding(image_rs::error::DecodingError::new(
+        image_rs::ImageFormat::Gif.into(),
+        error,
+    ))
+    .into()
+}
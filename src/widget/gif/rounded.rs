@@ -0,0 +1,131 @@
+//! Round the corners of every frame
+use std::cell::RefCell;
+
+use iced_widget::core::image::Handle;
+
+use super::{rgba, Thumbnail};
+
+/// The frames of a [`Gif::border_radius`] with their corners cut
+///
+/// Each frame is cut the first time it's drawn, then kept until the
+/// radius changes, so lazily decoded frames stay lazy.
+///
+/// [`Gif::border_radius`]: super::Gif::border_radius
+pub(super) struct Rounded {
+    /// The horizontal and vertical radius of each corner, clockwise
+    /// from the top left, in pixels of the rounded frames
+    radii: [(u32, u32); 4],
+    handles: RefCell<Vec<Option<Handle>>>,
+}
+
+impl Rounded {
+    pub fn new(len: usize, radii: [(u32, u32); 4]) -> Self {
+        Self {
+            radii,
+            handles: RefCell::new(vec![None; len]),
+        }
+    }
+
+    /// The number of frames this has room for
+    pub fn len(&self) -> usize {
+        self.handles.borrow().len()
+    }
+
+    /// Returns the frame at `index` cut from `handle`, which is only
+    /// cut the first time
+    pub fn handle(&self, index: usize, handle: &Handle) -> Handle {
+        let mut handles = self.handles.borrow_mut();
+
+        if let Some(Some(rounded)) = handles.get(index) {
+            return rounded.clone();
+        }
+
+        let rounded = match rgba(handle) {
+            Some(mut rgba) => {
+                round_corners(&mut rgba, self.radii);
+
+                Handle::from_rgba(rgba.width(), rgba.height(), rgba.into_raw())
+            }
+            None => handle.clone(),
+        };
+
+        if let Some(slot) = handles.get_mut(index) {
+            *slot = Some(rounded.clone());
+        }
+
+        rounded
+    }
+
+    /// Whether the corners should be cut again for `radii`
+    ///
+    /// Like a [`Thumbnail`], the radii may drift by a fraction before
+    /// it's worth cutting again, so resizing a window doesn't redo
+    /// every frame on each relayout.
+    pub fn is_stale(&self, radii: [(u32, u32); 4]) -> bool {
+        let drifted = |radius: u32, target: u32| {
+            radius.abs_diff(target) as f32 > (radius as f32 * Thumbnail::TOLERANCE).max(1.0)
+        };
+
+        self.radii
+            .iter()
+            .zip(radii)
+            .any(|(&(x, y), (target_x, target_y))| drifted(x, target_x) || drifted(y, target_y))
+    }
+}
+
+/// Fades out the pixels outside each rounded corner, clockwise
+/// from the top left
+///
+/// Each corner is a quarter ellipse of its horizontal and vertical
+/// radius, which differ once the frames are stretched on one axis.
+fn round_corners(rgba: &mut image_rs::RgbaImage, radii: [(u32, u32); 4]) {
+    let (width, height) = rgba.dimensions();
+
+    for (corner, (radius_x, radius_y)) in radii.into_iter().enumerate() {
+        let radius_x = radius_x.min(width / 2);
+        let radius_y = radius_y.min(height / 2);
+
+        if radius_x == 0 || radius_y == 0 {
+            continue;
+        }
+
+        let (left, top) = match corner {
+            0 => (0, 0),
+            1 => (width - radius_x, 0),
+            2 => (width - radius_x, height - radius_y),
+            _ => (0, height - radius_y),
+        };
+
+        // The center of the corner's ellipse
+        let (center_x, center_y) = match corner {
+            0 => (radius_x, radius_y),
+            1 => (width - radius_x, radius_y),
+            2 => (width - radius_x, height - radius_y),
+            _ => (radius_x, height - radius_y),
+        };
+
+        let (radius_x, radius_y) = (radius_x as f32, radius_y as f32);
+
+        for y in top..top + radius_y as u32 {
+            for x in left..left + radius_x as u32 {
+                let dx = x as f32 + 0.5 - center_x as f32;
+                let dy = y as f32 + 0.5 - center_y as f32;
+
+                // How far out the pixel is, where the edge is at 1
+                let distance = (dx / radius_x).hypot(dy / radius_y);
+
+                // Antialias the edge over a single pixel, using the
+                // gradient to turn the distance into pixels
+                let gradient =
+                    (dx / (radius_x * radius_x)).hypot(dy / (radius_y * radius_y)) / distance;
+                let coverage = match (1.0 - distance) / gradient {
+                    inside if inside.is_finite() => (inside + 0.5).clamp(0.0, 1.0),
+                    _ => 1.0,
+                };
+
+                let alpha = &mut rgba.get_pixel_mut(x, y).0[3];
+                *alpha = (f32::from(*alpha) * coverage).round() as u8;
+            }
+        }
+    }
+}
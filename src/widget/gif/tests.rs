@@ -0,0 +1,1116 @@
+use super::*;
+
+use iced_widget::core::renderer::Quad;
+use iced_widget::core::{clipboard, Background, Transformation};
+
+const FERRIS: &[u8] = include_bytes!("../../../assets/rust-lang-ferris.gif");
+
+/// Measures and records every image drawn, unlike the `()` renderer
+#[derive(Default)]
+struct Recorder {
+    drawn: Vec<(Handle, Rectangle)>,
+}
+
+impl renderer::Renderer for Recorder {
+    fn start_layer(&mut self, _bounds: Rectangle) {}
+
+    fn end_layer(&mut self) {}
+
+    fn start_transformation(&mut self, _transformation: Transformation) {}
+
+    fn end_transformation(&mut self) {}
+
+    fn fill_quad(&mut self, _quad: Quad, _background: impl Into<Background>) {}
+
+    fn clear(&mut self) {}
+}
+
+impl image::Renderer for Recorder {
+    type Handle = Handle;
+
+    fn measure_image(&self, handle: &Handle) -> Size<u32> {
+        match handle {
+            Handle::Rgba { width, height, .. } => Size::new(*width, *height),
+            _ => Size::default(),
+        }
+    }
+
+    fn draw_image(&mut self, image: Image, bounds: Rectangle) {
+        self.drawn.push((image.handle, bounds));
+    }
+}
+
+/// Encodes a looping gif of the given frames over a `width` by
+/// `height` canvas, with `palette` as the global color table
+fn encode(width: u16, height: u16, palette: &[u8], frames: &[gif_rs::Frame<'_>]) -> Vec<u8> {
+    let mut bytes = vec![];
+
+    {
+        let mut encoder = gif_rs::Encoder::new(&mut bytes, width, height, palette).unwrap();
+        encoder.set_repeat(gif_rs::Repeat::Infinite).unwrap();
+
+        for frame in frames {
+            encoder.write_frame(frame).unwrap();
+        }
+    }
+
+    bytes
+}
+
+/// A frame of palette indices at the given offset, shown for 100ms
+fn indexed(left: u16, top: u16, width: u16, height: u16, buffer: &[u8]) -> gif_rs::Frame<'_> {
+    gif_rs::Frame {
+        left,
+        top,
+        width,
+        height,
+        delay: 10,
+        buffer: std::borrow::Cow::Borrowed(buffer),
+        ..gif_rs::Frame::default()
+    }
+}
+
+/// Encodes a still, lossless WebP of the given RGBA pixels
+#[cfg(feature = "webp")]
+fn webp(width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![];
+
+    webp::WebPEncoder::new_lossless(&mut bytes)
+        .encode(pixels, width, height, image_rs::ColorType::Rgba8)
+        .unwrap();
+
+    bytes
+}
+
+/// Encodes an RGBA APNG of `frames`, each blended onto the last
+/// with `blend`
+fn apng(width: u32, height: u32, blend: ::png::BlendOp, frames: &[&[u8]]) -> Vec<u8> {
+    let mut bytes = vec![];
+
+    {
+        let mut encoder = ::png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(::png::ColorType::Rgba);
+        encoder.set_animated(frames.len() as u32, 0).unwrap();
+
+        let mut writer = encoder.write_header().unwrap();
+        writer.set_blend_op(blend).unwrap();
+
+        for frame in frames {
+            writer.write_image_data(frame).unwrap();
+        }
+
+        writer.finish().unwrap();
+    }
+
+    bytes
+}
+
+/// `count` frames of a single pixel, each a different shade of red
+fn frames(count: u8) -> Frames {
+    let palette: Vec<u8> = (0..count).flat_map(|index| [index, 0, 0]).collect();
+    let indices: Vec<_> = (0..count).map(|index| [index]).collect();
+    let frames: Vec<_> = indices
+        .iter()
+        .map(|index| indexed(0, 0, 1, 1, index))
+        .collect();
+
+    Frames::from_bytes(encode(1, 1, &palette, &frames)).unwrap()
+}
+
+/// The RGBA of the pixel at `x` and `y` of the frame at `index`
+fn pixel(frames: &Frames, index: usize, x: u32, y: u32) -> [u8; 4] {
+    rgba(&frames.frame(index).handle, x, y)
+}
+
+fn rgba(handle: &Handle, x: u32, y: u32) -> [u8; 4] {
+    let Handle::Rgba { width, pixels, .. } = handle else {
+        unreachable!("frames are decoded to RGBA");
+    };
+    let offset = ((y * width + x) * 4) as usize;
+
+    pixels[offset..offset + 4].try_into().unwrap()
+}
+
+/// `width` by `height` frames of a single opaque color each
+fn solid(colors: &[[u8; 3]], width: u16, height: u16) -> Frames {
+    let palette = colors.concat();
+    let indices: Vec<_> = (0..colors.len() as u8)
+        .map(|index| vec![index; usize::from(width) * usize::from(height)])
+        .collect();
+    let frames: Vec<_> = indices
+        .iter()
+        .map(|indices| indexed(0, 0, width, height, indices))
+        .collect();
+
+    Frames::from_bytes(encode(width, height, &palette, &frames)).unwrap()
+}
+
+fn tree(gif: &Gif<'_, ()>) -> Tree {
+    Tree::new(gif as &dyn Widget<(), (), ()>)
+}
+
+fn state(tree: &Tree) -> &State {
+    tree.state.downcast_ref::<State>()
+}
+
+/// Sends a redraw at `now` through [`Widget::on_event`], returning
+/// the published messages and the requested redraw
+fn redraw_at(
+    gif: &mut Gif<'_, ()>,
+    tree: &mut Tree,
+    now: Instant,
+) -> (Vec<()>, Option<window::RedrawRequest>) {
+    let node = layout::Node::new(Size::new(100.0, 100.0));
+    let viewport = node.bounds();
+
+    let mut messages = vec![];
+    let mut shell = Shell::new(&mut messages);
+
+    let _ = Widget::<(), (), ()>::on_event(
+        gif,
+        tree,
+        Event::Window(window::Event::RedrawRequested(now)),
+        Layout::new(&node),
+        Cursor::Unavailable,
+        &(),
+        &mut clipboard::Null,
+        &mut shell,
+        &viewport,
+    );
+
+    let request = shell.redraw_request();
+
+    (messages, request)
+}
+
+/// Redraws just past the current frame's delay, so playback
+/// steps a single frame
+fn advance(gif: &mut Gif<'_, ()>, tree: &mut Tree) -> usize {
+    let state = state(tree);
+    let now = state.current.started + state.delay() + Duration::from_millis(1);
+
+    redraw_at(gif, tree, now);
+
+    self::state(tree).index
+}
+
+/// Runs [`Widget::operate`], returning the [`Playback`] that
+/// [`query_playback`] produces
+fn playback(gif: &Gif<'_, ()>, tree: &mut Tree) -> Playback {
+    struct Query(Option<Playback>);
+
+    impl Operation for Query {
+        fn container(
+            &mut self,
+            _id: Option<&Id>,
+            _bounds: Rectangle,
+            _operate_on_children: &mut dyn FnMut(&mut dyn Operation),
+        ) {
+        }
+
+        fn custom(&mut self, state: &mut dyn std::any::Any, _id: Option<&Id>) {
+            let state = state.downcast_ref::<State>().unwrap();
+
+            self.0 = Some(Playback {
+                index: state.index,
+                total: state.len,
+                elapsed: state.elapsed,
+            });
+        }
+    }
+
+    let node = layout::Node::new(Size::new(100.0, 100.0));
+    let mut query = Query(None);
+
+    Widget::<(), (), ()>::operate(gif, tree, Layout::new(&node), &(), &mut query);
+
+    query.0.unwrap()
+}
+
+/// Runs the widget operations of `task` on `gif`
+fn run(gif: &Gif<'_, ()>, tree: &mut Tree, task: Task<()>) {
+    use iced_futures::futures::{executor, StreamExt};
+
+    let node = layout::Node::new(Size::new(100.0, 100.0));
+    let actions: Vec<_> = executor::block_on(task::into_stream(task).unwrap().collect());
+
+    for action in actions {
+        if let Action::Widget(mut operation) = action {
+            Widget::<(), (), ()>::operate(gif, tree, Layout::new(&node), &(), operation.as_mut());
+        }
+    }
+}
+
+fn layout(gif: &Gif<'_, ()>, tree: &mut Tree) -> layout::Node {
+    let limits = layout::Limits::new(Size::ZERO, Size::INFINITY);
+
+    Widget::<(), (), Recorder>::layout(gif, tree, &Recorder::default(), &limits)
+}
+
+/// Lays out and draws `gif`, returning the layout bounds and
+/// everything drawn
+fn draw(gif: &Gif<'_, ()>, tree: &mut Tree) -> (Rectangle, Vec<(Handle, Rectangle)>) {
+    let node = layout(gif, tree);
+    let mut recorder = Recorder::default();
+
+    Widget::<(), (), Recorder>::draw(
+        gif,
+        tree,
+        &mut recorder,
+        &(),
+        &renderer::Style::default(),
+        Layout::new(&node),
+        Cursor::Unavailable,
+        &node.bounds(),
+    );
+
+    (node.bounds(), recorder.drawn)
+}
+
+#[test]
+fn frame_overrides_playback() {
+    let frames = frames(3);
+    let mut gif = Gif::new(&frames).frame(4);
+    let mut tree = tree(&gif);
+
+    assert_eq!(advance(&mut gif, &mut tree), 1);
+
+    let (_, drawn) = draw(&gif, &mut tree);
+
+    assert_eq!(drawn.len(), 1);
+    assert_eq!(drawn[0].0.id(), frames.frame(1).handle.id());
+}
+
+#[test]
+fn frame_override_requests_no_redraw() {
+    let frames = frames(3);
+    let mut gif = Gif::new(&frames).frame(1);
+    let mut tree = tree(&gif);
+
+    let (_, request) = redraw_at(&mut gif, &mut tree, Instant::now() + Duration::from_secs(1));
+
+    assert_eq!(request, None);
+}
+
+#[test]
+fn ferris_counts() {
+    let frames = Frames::from_bytes(FERRIS.to_vec()).unwrap();
+
+    assert_eq!(frames.len(), 127);
+    assert!(!frames.is_empty());
+    assert_eq!(frames.total_duration(), Duration::from_millis(12_700));
+}
+
+#[cfg(feature = "webp")]
+#[test]
+fn still_webp_decodes_to_one_frame() {
+    let frames = Frames::from_webp_bytes(webp(2, 1, &[255, 0, 0, 255, 0, 0, 255, 128])).unwrap();
+
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames.loop_count(), LoopCount::Infinite);
+    assert!(matches!(
+        &frames.frame(0).handle,
+        Handle::Rgba { width: 2, height: 1, pixels, .. }
+            if pixels[..] == [255, 0, 0, 255, 0, 0, 255, 128]
+    ));
+}
+
+#[cfg(feature = "apng")]
+#[test]
+fn apng_decodes_every_frame() {
+    let bytes = apng(
+        1,
+        1,
+        ::png::BlendOp::Source,
+        &[&[255, 0, 0, 255], &[0, 255, 0, 255], &[0, 0, 255, 255]],
+    );
+
+    let frames = Frames::from_apng_bytes(bytes).unwrap();
+
+    assert_eq!(frames.len(), 3);
+    assert!(matches!(
+        &frames.frame(2).handle,
+        Handle::Rgba { pixels, .. } if pixels[..] == [0, 0, 255, 255]
+    ));
+}
+
+#[test]
+fn disposal_composites_partial_frames() {
+    let palette = [255, 0, 0, 0, 0, 255, 0, 255, 0];
+
+    let bytes = encode(
+        2,
+        2,
+        &palette,
+        &[
+            indexed(0, 0, 2, 2, &[0; 4]),
+            gif_rs::Frame {
+                dispose: gif_rs::DisposalMethod::Background,
+                ..indexed(0, 0, 1, 1, &[1])
+            },
+            indexed(1, 1, 1, 1, &[2]),
+        ],
+    );
+
+    let frames = Frames::from_bytes(bytes).unwrap();
+
+    assert_eq!(frames.len(), 3);
+
+    // Kept from the first frame
+    assert_eq!(pixel(&frames, 1, 0, 0), [0, 0, 255, 255]);
+    assert_eq!(pixel(&frames, 1, 1, 1), [255, 0, 0, 255]);
+
+    // Cleared once the second frame is disposed
+    assert_eq!(pixel(&frames, 2, 0, 0)[3], 0);
+    assert_eq!(pixel(&frames, 2, 1, 0), [255, 0, 0, 255]);
+    assert_eq!(pixel(&frames, 2, 1, 1), [0, 255, 0, 255]);
+}
+
+#[test]
+fn backward_index_sequence() {
+    let frames = frames(3);
+    let mut gif = Gif::new(&frames).direction(Direction::Backward);
+    let mut tree = tree(&gif);
+
+    assert_eq!(state(&tree).index, 2);
+
+    let indices: Vec<_> = (0..4).map(|_| advance(&mut gif, &mut tree)).collect();
+
+    assert_eq!(indices, [1, 0, 2, 1]);
+}
+
+#[test]
+fn forward_index_sequence() {
+    let frames = frames(3);
+    let mut gif = Gif::new(&frames);
+    let mut tree = tree(&gif);
+
+    let indices: Vec<_> = (0..4).map(|_| advance(&mut gif, &mut tree)).collect();
+
+    assert_eq!(indices, [1, 2, 0, 1]);
+}
+
+#[test]
+fn lazy_frames_match_eager() {
+    let eager = Frames::from_bytes(FERRIS.to_vec()).unwrap();
+    let lazy = Frames::from_bytes_lazy(FERRIS.to_vec()).unwrap();
+
+    assert_eq!(lazy.len(), eager.len());
+    assert_eq!(lazy.total_duration(), eager.total_duration());
+
+    let pixels = |frames: &Frames, index| match frames.frame(index).handle {
+        Handle::Rgba { pixels, .. } => pixels,
+        _ => unreachable!("frames are decoded to RGBA"),
+    };
+
+    // Jumping back out of the cached window decodes from the start
+    for index in [126, 0, 64, 65] {
+        assert_eq!(pixels(&lazy, index), pixels(&eager, index));
+    }
+}
+
+#[test]
+fn from_bytes_rejects_other_formats() {
+    let png = apng(1, 1, ::png::BlendOp::Source, &[&[0; 4]]);
+
+    assert!(matches!(
+        Frames::from_bytes(png),
+        Err(Error::UnsupportedFormat {
+            detected: Some(image_rs::ImageFormat::Png)
+        })
+    ));
+}
+
+#[test]
+fn paused_on_starts_from_its_frame() {
+    let frames = frames(3);
+    let mut gif = Gif::new(&frames).paused_on(4);
+    let mut tree = tree(&gif);
+
+    assert_eq!(state(&tree).index, 1);
+    assert_eq!(advance(&mut gif, &mut tree), 1);
+
+    let mut gif = Gif::new(&frames).paused_on(4).playing(true);
+    Widget::<(), (), ()>::diff(&gif, &mut tree);
+
+    assert_eq!(advance(&mut gif, &mut tree), 2);
+}
+
+#[test]
+fn empty_frames() {
+    let frames = Frames::empty();
+
+    assert_eq!(frames.len(), 0);
+    assert!(frames.is_empty());
+    assert_eq!(frames.total_duration(), Duration::ZERO);
+    assert_eq!(frames.average_fps(), 0.0);
+
+    // The widget draws nothing, and never schedules a redraw
+    let mut gif = Gif::new(&frames);
+    let mut tree = tree(&gif);
+
+    let (_, request) = redraw_at(&mut gif, &mut tree, Instant::now());
+    let (_, drawn) = draw(&gif, &mut tree);
+
+    assert_eq!(request, None);
+    assert!(drawn.is_empty());
+}
+
+#[test]
+fn border_radius_rounds_drawn_corners() {
+    let frames = solid(&[[255, 0, 0]], 20, 20);
+    let gif = Gif::new(&frames).border_radius(8.0);
+    let mut tree = tree(&gif);
+
+    let (_, drawn) = draw(&gif, &mut tree);
+    let handle = &drawn[0].0;
+
+    assert_eq!(rgba(handle, 0, 0)[3], 0);
+    assert_eq!(rgba(handle, 0, 10), [255, 0, 0, 255]);
+    assert_eq!(rgba(handle, 10, 10), [255, 0, 0, 255]);
+}
+
+#[test]
+fn scale_down_never_upscales() {
+    let frames = solid(&[[255, 0, 0]], 16, 16);
+    let gif = Gif::new(&frames)
+        .width(Length::Fixed(200.0))
+        .height(Length::Fixed(200.0))
+        .content_fit(ContentFit::ScaleDown);
+    let mut tree = tree(&gif);
+
+    let (_, drawn) = draw(&gif, &mut tree);
+
+    assert_eq!(
+        drawn[0].1,
+        Rectangle::new(Point::new(92.0, 92.0), Size::new(16.0, 16.0))
+    );
+}
+
+#[test]
+fn truncated_count() {
+    let full = Frames::from_bytes(FERRIS.to_vec()).unwrap();
+    let truncated = Frames::from_bytes_truncated(FERRIS.to_vec(), 3).unwrap();
+
+    assert_eq!(truncated.len(), 3);
+    assert_eq!(truncated.frame_delays(), full.frame_delays()[..3]);
+}
+
+#[test]
+fn equal_length_gifs_differ() {
+    let palette = [0, 255, 0, 0, 0, 255, 255, 0, 0, 0, 0, 0];
+
+    let green = encode(
+        1,
+        1,
+        &palette,
+        &[indexed(0, 0, 1, 1, &[0]), indexed(0, 0, 1, 1, &[2])],
+    );
+    let blue = encode(
+        1,
+        1,
+        &palette,
+        &[indexed(0, 0, 1, 1, &[1]), indexed(0, 0, 1, 1, &[2])],
+    );
+
+    assert_eq!(green.len(), blue.len());
+
+    let green = Frames::from_bytes(green).unwrap();
+    let blue = Frames::from_bytes(blue).unwrap();
+
+    assert_ne!(green.hash, blue.hash);
+
+    // Swapping in the other gif restarts playback
+    let mut gif = Gif::new(&green);
+    let mut tree = tree(&gif);
+
+    assert_eq!(advance(&mut gif, &mut tree), 1);
+
+    Widget::<(), (), ()>::diff(&Gif::new(&green), &mut tree);
+    assert_eq!(state(&tree).index, 1);
+
+    Widget::<(), (), ()>::diff(&Gif::new(&blue), &mut tree);
+    assert_eq!(state(&tree).index, 0);
+}
+
+#[test]
+fn scale_doubles_layout() {
+    let frames = solid(&[[255, 0, 0]], 2, 3);
+    let gif = Gif::new(&frames).scale(2.0);
+    let mut tree = tree(&gif);
+
+    assert_eq!(layout(&gif, &mut tree).size(), Size::new(4.0, 6.0));
+}
+
+#[test]
+fn resized_dimensions() {
+    let frames = solid(&[[255, 0, 0], [0, 255, 0]], 2, 2);
+    let resized = frames.resized(4, 6, image_rs::imageops::FilterType::Nearest);
+
+    assert_eq!(frames.dimensions(), (2, 2));
+    assert_eq!(resized.dimensions(), (4, 6));
+    assert_eq!(resized.len(), 2);
+    assert_eq!(resized.frame_delays(), frames.frame_delays());
+    assert_eq!(pixel(&resized, 1, 3, 5), [0, 255, 0, 255]);
+}
+
+#[test]
+fn auto_dispatches_on_magic_bytes() {
+    let gif = encode(1, 1, &[255, 0, 0], &[indexed(0, 0, 1, 1, &[0])]);
+    let gif = Frames::from_bytes_auto(gif).unwrap();
+
+    assert_eq!(pixel(&gif, 0, 0, 0), [255, 0, 0, 255]);
+
+    assert!(matches!(
+        Frames::from_bytes_auto(b"\xFF\xD8\xFF\xE0".to_vec()),
+        Err(Error::UnsupportedFormat {
+            detected: Some(image_rs::ImageFormat::Jpeg)
+        })
+    ));
+    assert!(matches!(
+        Frames::from_bytes_auto(b"not an image".to_vec()),
+        Err(Error::UnsupportedFormat { detected: None })
+    ));
+}
+
+#[cfg(feature = "webp")]
+#[test]
+fn auto_decodes_webp() {
+    let frames = Frames::from_bytes_auto(webp(1, 1, &[0, 255, 0, 255])).unwrap();
+
+    assert_eq!(pixel(&frames, 0, 0, 0), [0, 255, 0, 255]);
+}
+
+#[cfg(not(feature = "webp"))]
+#[test]
+fn auto_rejects_webp_without_the_feature() {
+    assert!(matches!(
+        Frames::from_bytes_auto(b"RIFF\0\0\0\0WEBPVP8 ".to_vec()),
+        Err(Error::UnsupportedFormat {
+            detected: Some(image_rs::ImageFormat::WebP)
+        })
+    ));
+}
+
+#[cfg(feature = "apng")]
+#[test]
+fn auto_decodes_apng() {
+    let bytes = apng(
+        1,
+        1,
+        ::png::BlendOp::Source,
+        &[&[0, 0, 255, 255], &[0, 255, 0, 255]],
+    );
+
+    let frames = Frames::from_bytes_auto(bytes).unwrap();
+
+    assert_eq!(frames.len(), 2);
+    assert_eq!(pixel(&frames, 1, 0, 0), [0, 255, 0, 255]);
+}
+
+#[cfg(not(feature = "apng"))]
+#[test]
+fn auto_rejects_apng_without_the_feature() {
+    let bytes = apng(1, 1, ::png::BlendOp::Source, &[&[0; 4]]);
+
+    assert!(matches!(
+        Frames::from_bytes_auto(bytes),
+        Err(Error::UnsupportedFormat {
+            detected: Some(image_rs::ImageFormat::Png)
+        })
+    ));
+}
+
+#[cfg(feature = "apng")]
+#[test]
+fn apng_blends_over() {
+    let bytes = apng(
+        2,
+        1,
+        ::png::BlendOp::Over,
+        &[
+            &[255, 0, 0, 255, 255, 0, 0, 255],
+            &[0, 0, 255, 255, 0, 0, 0, 0],
+        ],
+    );
+
+    let frames = Frames::from_apng_bytes(bytes).unwrap();
+
+    assert_eq!(frames.len(), 2);
+    assert_eq!(pixel(&frames, 1, 0, 0), [0, 0, 255, 255]);
+    assert_eq!(pixel(&frames, 1, 1, 0), [255, 0, 0, 255]);
+}
+
+#[test]
+fn single_frame_requests_no_redraw() {
+    let frames = frames(1);
+    let mut gif = Gif::new(&frames);
+    let mut tree = tree(&gif);
+
+    let (messages, request) =
+        redraw_at(&mut gif, &mut tree, Instant::now() + Duration::from_secs(1));
+
+    assert!(messages.is_empty());
+    assert_eq!(request, None);
+    assert_eq!(state(&tree).index, 0);
+}
+
+#[test]
+fn fill_covers_bounds() {
+    let frames = solid(&[[255, 0, 0]], 2, 2);
+    let gif = Gif::new(&frames)
+        .width(Length::Fixed(100.0))
+        .height(Length::Fixed(50.0))
+        .content_fit(ContentFit::Fill);
+    let mut tree = tree(&gif);
+
+    let (bounds, drawn) = draw(&gif, &mut tree);
+
+    assert_eq!(
+        bounds,
+        Rectangle::new(Point::ORIGIN, Size::new(100.0, 50.0))
+    );
+    assert_eq!(drawn[0].1, bounds);
+}
+
+#[test]
+fn ferris_average_fps() {
+    let frames = Frames::from_bytes(FERRIS.to_vec()).unwrap();
+
+    assert_eq!(frames.average_fps(), 10.0);
+}
+
+#[test]
+fn frames_equal_by_content() {
+    let bytes = encode(1, 1, &[255, 0, 0], &[indexed(0, 0, 1, 1, &[0])]);
+
+    let first = Frames::from_bytes(bytes.clone()).unwrap();
+    let second = Frames::from_bytes(bytes).unwrap();
+
+    assert_eq!(first, second);
+    assert_ne!(first, frames(1));
+
+    let hash = |frames: &Frames| {
+        let mut hasher = DefaultHasher::new();
+        frames.hash(&mut hasher);
+        hasher.finish()
+    };
+
+    assert_eq!(hash(&first), hash(&second));
+}
+
+#[test]
+fn interlaced_rows_in_order() {
+    let palette: Vec<u8> = (0..8).flat_map(|row| [row * 30, 0, 0]).collect();
+
+    // Rows are stored in the four interlace passes
+    let bytes = encode(
+        1,
+        8,
+        &palette,
+        &[gif_rs::Frame {
+            interlaced: true,
+            ..indexed(0, 0, 1, 8, &[0, 4, 2, 6, 1, 3, 5, 7])
+        }],
+    );
+
+    let frames = Frames::from_bytes(bytes).unwrap();
+
+    for row in 0..8 {
+        assert_eq!(pixel(&frames, 0, 0, row), [row as u8 * 30, 0, 0, 255]);
+    }
+}
+
+#[test]
+fn reversed_order() {
+    let frames = frames(3);
+    let reversed = frames.reversed();
+
+    let ids = |frames: &Frames| {
+        (0..frames.len())
+            .map(|index| frames.frame_handle(index).unwrap().id())
+            .collect::<Vec<_>>()
+    };
+
+    let mut expected = ids(&frames);
+    expected.reverse();
+
+    assert_eq!(ids(&reversed), expected);
+    assert_eq!(reversed.poster().id(), frames.frame_handle(2).unwrap().id());
+    assert_ne!(reversed, frames);
+}
+
+#[cfg(feature = "apng")]
+#[test]
+fn sixteen_bit_scaled_to_eight() {
+    let mut bytes = vec![];
+
+    {
+        let mut encoder = ::png::Encoder::new(&mut bytes, 1, 1);
+        encoder.set_color(::png::ColorType::Rgba);
+        encoder.set_depth(::png::BitDepth::Sixteen);
+
+        let mut writer = encoder.write_header().unwrap();
+
+        writer
+            .write_image_data(&[0xFF, 0xFF, 0x80, 0x80, 0x00, 0x00, 0xFF, 0xFF])
+            .unwrap();
+    }
+
+    let frames = Frames::from_apng_bytes(bytes).unwrap();
+
+    assert_eq!(pixel(&frames, 0, 0, 0), [255, 128, 0, 255]);
+}
+
+#[test]
+fn header_only_has_no_frames() {
+    let bytes = b"GIF89a\x01\x00\x01\x00\x00\x00\x00\x3b".to_vec();
+
+    assert!(matches!(
+        Frames::from_bytes(bytes.clone()),
+        Err(Error::NoFrames)
+    ));
+    assert!(matches!(
+        Frames::from_bytes_lazy(bytes.clone()),
+        Err(Error::NoFrames)
+    ));
+    assert!(matches!(
+        Frames::poster_from_bytes(bytes),
+        Err(Error::NoFrames)
+    ));
+}
+
+#[test]
+fn fill_rounds_each_axis() {
+    let frames = solid(&[[255, 0, 0]], 40, 20);
+    let gif = Gif::new(&frames)
+        .width(Length::Fixed(40.0))
+        .height(Length::Fixed(40.0))
+        .content_fit(ContentFit::Fill)
+        .border_radius(8.0);
+    let mut tree = tree(&gif);
+
+    let (_, drawn) = draw(&gif, &mut tree);
+
+    // Stretched to twice the height, so the vertical radius
+    // is half the horizontal one in frame pixels
+    let alpha = |x, y| rgba(&drawn[0].0, x, y)[3];
+
+    assert_eq!(alpha(0, 0), 0);
+    assert!(alpha(6, 0) < 255);
+    assert_eq!(alpha(0, 4), 255);
+    assert_eq!(alpha(20, 10), 255);
+}
+
+#[test]
+fn border_radius_survives_small_resizes() {
+    let frames = solid(&[[255, 0, 0]], 200, 200);
+    let gif = |size| {
+        Gif::new(&frames)
+            .width(Length::Fixed(size))
+            .height(Length::Fixed(size))
+            .border_radius(8.0)
+    };
+    let mut tree = tree(&gif(100.0));
+
+    let (_, before) = draw(&gif(100.0), &mut tree);
+    let (_, nudged) = draw(&gif(104.0), &mut tree);
+    let (_, doubled) = draw(&gif(200.0), &mut tree);
+
+    assert_eq!(before[0].0.id(), nudged[0].0.id());
+    assert_ne!(before[0].0.id(), doubled[0].0.id());
+}
+
+#[test]
+fn max_fps_holds_back_any_redraw() {
+    let frames = frames(3);
+    let mut gif = Gif::new(&frames).max_fps(4);
+    let mut tree = tree(&gif);
+
+    assert_eq!(advance(&mut gif, &mut tree), 1);
+
+    let advanced = state(&tree).advanced.unwrap();
+    let interval = Duration::from_millis(250);
+
+    // Past the frame's delay, but not the interval
+    let (_, request) = redraw_at(&mut gif, &mut tree, advanced + Duration::from_millis(150));
+
+    assert_eq!(state(&tree).index, 1);
+    assert_eq!(
+        request,
+        Some(window::RedrawRequest::At(advanced + interval))
+    );
+
+    // Skipping the frame that was due in between
+    redraw_at(&mut gif, &mut tree, advanced + interval);
+
+    assert_eq!(state(&tree).index, 0);
+}
+
+#[test]
+fn builder_overlays_smaller_frames() {
+    let frames = FramesBuilder::new()
+        .push_frame([255, 0, 0, 255].repeat(4), 2, 2, Duration::from_millis(100))
+        .push_frame(vec![0; 4], 1, 1, Duration::from_millis(100))
+        .build();
+
+    assert_eq!(frames.dimensions(), (2, 2));
+    assert_eq!(pixel(&frames, 1, 0, 0), [255, 0, 0, 255]);
+}
+
+#[test]
+fn playback_elapsed_follows_clock() {
+    let frames = frames(3);
+    let at = |millis| Gif::new(&frames).clock(Duration::from_millis(millis));
+
+    let mut tree = tree(&at(0));
+
+    assert_eq!(
+        playback(&at(30), &mut tree).elapsed,
+        Duration::from_millis(30)
+    );
+
+    Widget::<(), (), ()>::diff(&at(40).playing(false), &mut tree);
+
+    // Held while paused, however far the clock moves
+    assert_eq!(
+        playback(&at(90).playing(false), &mut tree).elapsed,
+        Duration::from_millis(40)
+    );
+}
+
+#[test]
+fn flipped_frames_are_shared() {
+    let frames = FramesBuilder::new()
+        .push_frame(
+            [[255, 0, 0, 255], [0, 0, 255, 255]].concat(),
+            2,
+            1,
+            Duration::from_millis(100),
+        )
+        .build();
+
+    let flipped = frames.flipped(true, false);
+
+    assert_eq!(pixel(&flipped, 0, 0, 0), [0, 0, 255, 255]);
+    assert_eq!(
+        frames.clone().flipped(true, false).frame(0).handle.id(),
+        flipped.frame(0).handle.id()
+    );
+
+    // Any widget drawing them draws the shared copy
+    let gif = Gif::new(&frames).flip_horizontal(true);
+    let (_, drawn) = draw(&gif, &mut tree(&gif));
+
+    assert_eq!(drawn[0].0.id(), flipped.frame(0).handle.id());
+}
+
+#[test]
+fn caches_grow_with_partial_frames() {
+    let partial = solid(&[[255, 0, 0]], 4, 4);
+    let mut frames = solid(&[[255, 0, 0], [0, 255, 0], [0, 0, 255]], 4, 4);
+
+    // Partial frames of a stream share a hash
+    frames.hash = partial.hash;
+
+    let gif = |frames| {
+        Gif::new(frames)
+            .thumbnail(Size::new(2, 2))
+            .flip_horizontal(true)
+            .border_radius(1.0)
+    };
+
+    let mut tree = tree(&gif(&partial));
+    let _ = layout(&gif(&partial), &mut tree);
+
+    Widget::<(), (), ()>::diff(&gif(&frames), &mut tree);
+    let _ = layout(&gif(&frames), &mut tree);
+
+    let state = state(&tree);
+
+    assert_eq!(state.thumbnail.as_ref().unwrap().handles.len(), 3);
+    assert_eq!(state.flipped.as_ref().unwrap().len(), 3);
+    assert_eq!(state.rounded.as_ref().unwrap().len(), 3);
+}
+
+#[cfg(all(feature = "async-fs", not(feature = "tokio")))]
+#[test]
+fn stream_from_reader_produces_partial_frames() {
+    use iced_futures::futures::{executor, future, io, StreamExt};
+
+    let palette = [255, 0, 0, 0, 255, 0, 0, 0, 255];
+    let bytes = encode(
+        1,
+        1,
+        &palette,
+        &[
+            indexed(0, 0, 1, 1, &[0]),
+            indexed(0, 0, 1, 1, &[1]),
+            indexed(0, 0, 1, 1, &[2]),
+        ],
+    );
+
+    let task = Frames::stream_from_reader(io::Cursor::new(bytes));
+    let produced: Vec<_> = executor::block_on(
+        task::into_stream(task)
+            .unwrap()
+            .filter_map(|action| {
+                future::ready(match action {
+                    Action::Output(frames) => Some(frames.unwrap()),
+                    _ => None,
+                })
+            })
+            .collect(),
+    );
+
+    let lens: Vec<_> = produced.iter().map(Frames::len).collect();
+
+    assert_eq!(lens, [1, 2, 3]);
+    assert!(produced.iter().all(|frames| *frames == produced[0]));
+    assert_eq!(pixel(&produced[2], 2, 0, 0), [0, 0, 255, 255]);
+}
+
+#[test]
+fn step_applies_on_the_next_event() {
+    let frames = frames(3);
+    let id = Id::unique();
+    let mut gif = Gif::new(&frames)
+        .id(id.clone())
+        .playing(false)
+        .on_frame_changed(|_, _| ())
+        .on_progress(|_| ());
+    let mut tree = tree(&gif);
+
+    run(&gif, &mut tree, step_backward(id.clone()));
+    run(&gif, &mut tree, step_backward(Id::unique()));
+
+    assert_eq!(state(&tree).index, 0);
+
+    let (messages, request) = redraw_at(&mut gif, &mut tree, Instant::now());
+
+    assert_eq!(state(&tree).index, 2);
+    assert_eq!(messages.len(), 2);
+    assert_eq!(request, Some(window::RedrawRequest::NextFrame));
+
+    // Nothing left to apply
+    let (messages, _) = redraw_at(&mut gif, &mut tree, Instant::now());
+
+    assert_eq!(state(&tree).index, 2);
+    assert!(messages.is_empty());
+}
+
+#[test]
+fn play_once_wins_over_a_later_loop_count() {
+    let frames = frames(2);
+    let mut gif = Gif::new(&frames)
+        .play_once_then(Duration::from_secs(1), EndAction::HoldLast)
+        .loop_count(LoopCount::Infinite);
+    let mut tree = tree(&gif);
+
+    assert_eq!(advance(&mut gif, &mut tree), 1);
+    assert_eq!(advance(&mut gif, &mut tree), 1);
+
+    let state = state(&tree);
+
+    assert!(state.finished);
+    assert!(state.ending.is_some());
+}
+
+#[test]
+fn owned_gif_clones_share_callbacks() {
+    let gif = Gif::owned(frames(2)).on_click(()).on_progress(|_| ());
+    let clone = gif.clone();
+
+    assert!(Rc::ptr_eq(
+        gif.on_click.as_ref().unwrap(),
+        clone.on_click.as_ref().unwrap()
+    ));
+
+    let (_, drawn) = draw(&clone, &mut tree(&clone));
+
+    assert_eq!(drawn[0].0.id(), gif.frames.frame(0).handle.id());
+}
+
+#[test]
+fn gradient_keeps_straight_alpha() {
+    // Gifs only have fully transparent pixels, so fade in by hand
+    // from a transparent black
+    let gradient = [
+        [0, 0, 0, 0],
+        [255, 128, 0, 85],
+        [255, 128, 0, 170],
+        [255, 128, 0, 255],
+    ]
+    .concat();
+
+    let frames = FramesBuilder::new()
+        .push_frame(gradient, 4, 1, Duration::from_millis(100))
+        .build();
+
+    let (_, _, pixels) = frames.frame_rgba(0).unwrap();
+
+    // The transparent end is bled from its neighbor, and nothing
+    // is premultiplied
+    assert_eq!(
+        pixels,
+        [
+            [255, 128, 0, 0],
+            [255, 128, 0, 85],
+            [255, 128, 0, 170],
+            [255, 128, 0, 255]
+        ]
+        .concat()
+    );
+}
+
+#[test]
+fn hold_first_returns_to_the_starting_frame() {
+    let frames = frames(3);
+    let mut gif = Gif::new(&frames)
+        .paused_on(1)
+        .playing(true)
+        .play_once_then(Duration::ZERO, EndAction::HoldFirst);
+    let mut tree = tree(&gif);
+
+    assert_eq!(advance(&mut gif, &mut tree), 2);
+
+    while state(&tree).ending.is_none() {
+        advance(&mut gif, &mut tree);
+    }
+
+    redraw_at(&mut gif, &mut tree, Instant::now() + Duration::from_secs(1));
+
+    assert_eq!(state(&tree).index, 1);
+}
+
+#[test]
+fn enter_frame_fires_on_every_arrival() {
+    let frames = frames(3);
+
+    // Redraws once the current frame is due, returning how many
+    // messages were produced
+    let next = |gif: &mut Gif<'_, ()>, tree: &mut Tree| {
+        let state = state(tree);
+        let now = state.current.started + state.delay() + Duration::from_millis(1);
+
+        redraw_at(gif, tree, now).0.len()
+    };
+
+    let mut gif = Gif::new(&frames).on_enter_frame(0, ());
+    let mut tree = tree(&gif);
+
+    // The frame playback starts on is reached as it starts
+    assert_eq!(redraw_at(&mut gif, &mut tree, Instant::now()).0.len(), 1);
+    assert_eq!(next(&mut gif, &mut tree), 0);
+    assert_eq!(next(&mut gif, &mut tree), 0);
+    assert_eq!(next(&mut gif, &mut tree), 1);
+
+    // And again on every loop through a range of just that frame
+    let mut gif = Gif::new(&frames).loop_range(1, 1).on_enter_frame(1, ());
+    let mut tree = self::tree(&gif);
+
+    for _ in 0..3 {
+        assert_eq!(next(&mut gif, &mut tree), 1);
+        assert_eq!(state(&tree).index, 1);
+    }
+}